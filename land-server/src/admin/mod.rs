@@ -63,6 +63,7 @@ pub async fn route(assets_dir: &str, tpl_dir: Option<String>) -> Result<Router>
         .route("/settings/domains", post(settings::update_domains))
         .route("/settings/prometheus", post(settings::update_prometheus))
         .route("/settings/storage", post(settings::update_storage))
+        .route("/settings/deploy", post(settings::update_deploy))
         .route("/workers", get(workers::index))
         .route("/workers/tokens/create", post(workers::create_token))
         .route("/workers/tokens/remove", post(workers::remove_token))
@@ -1,10 +1,10 @@
 use crate::{
-    dash::{ok_html, ServerError},
+    dash::{error_html, ok_html, ServerError},
     templates::{Engine, RenderHtmlMinified},
 };
 use axum::{response::IntoResponse, Extension, Form};
 use land_core::{storage, traffic};
-use land_dao::settings::{self, DomainSettings};
+use land_dao::settings::{self, DeploySettings, DomainSettings};
 use land_vars::{AuthUser, BreadCrumbKey, Page};
 use serde::{Deserialize, Serialize};
 
@@ -19,6 +19,7 @@ pub async fn index(
         pub domain_settings: DomainSettings,
         pub prometheus: traffic::Settings,
         pub storage: storage::Vars,
+        pub deploy_settings: DeploySettings,
     }
     let domain_settings = settings::get_domain_settings().await?;
     let prometheus = traffic::get_settings().await?;
@@ -31,6 +32,7 @@ pub async fn index(
             prometheus,
             domain_settings,
             storage: storage::Vars::get().await?,
+            deploy_settings: settings::get_deploy_settings().await?,
         },
     ))
 }
@@ -57,6 +59,17 @@ pub async fn update_prometheus(
     Ok(ok_html("Updated successfully"))
 }
 
+/// update_deploy updates the deploy settings, /admin/settings/deploy
+pub async fn update_deploy(
+    Form(f): Form<DeploySettings>,
+) -> Result<impl IntoResponse, ServerError> {
+    if f.success_policy.parse::<settings::SuccessPolicy>().is_err() {
+        return Ok(error_html("Invalid success policy").into_response());
+    }
+    settings::set_deploy_settings(f).await?;
+    Ok(ok_html("Updated successfully").into_response())
+}
+
 /// update_storage for admin storage, POST /admin/storage
 pub async fn update_storage(
     Form(form): Form<storage::Form>,
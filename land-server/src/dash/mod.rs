@@ -2,12 +2,14 @@ use crate::templates::{new_handlebar, Engine, RenderHtmlMinified};
 use anyhow::{anyhow, Result};
 use axum::{
     body::Body,
+    extract::DefaultBodyLimit,
     http::StatusCode,
     middleware,
     response::{Html, IntoResponse, Response},
     routing::{get, post},
     Extension, Router,
 };
+use land_core::deployer;
 use land_vars::{AuthUser, BreadCrumbKey, Page, Project};
 use serde::Serialize;
 use tower_http::services::ServeDir;
@@ -95,19 +97,44 @@ pub async fn route(assets_dir: &str, tpl_dir: Option<String>) -> Result<Router>
             "/projects/:name/edit",
             get(projects::edit).post(projects::handle_edit),
         )
+        .route("/projects/:name/preview", post(projects::handle_preview))
+        .route(
+            "/projects/:name/upload",
+            post(projects::handle_upload).layer(DefaultBodyLimit::max(deployer::MAX_UPLOAD_SIZE)),
+        )
         .route("/projects/:name/status", post(projects::handle_status))
         .route("/projects/:name/traffic", get(projects::traffic))
+        .route("/projects/:name/logs", get(projects::logs))
+        .route("/projects/:name/logs/tail", get(projects::logs_tail))
+        .route(
+            "/projects/:name/inspect",
+            get(projects::inspect).post(projects::handle_inspect),
+        )
+        .route("/projects/:name/inspect/tail", get(projects::inspect_tail))
         .route(
             "/projects/:name/settings",
             get(projects::settings).post(projects::handle_settings),
         )
+        .route("/projects/:name/envs", post(projects::handle_envs))
+        .route("/projects/:name/rollback", post(projects::handle_rollback))
+        .route("/projects/:name/promote", post(projects::handle_promote))
+        .route(
+            "/projects/:name/deployments/:deploy_id/logs",
+            get(projects::build_logs),
+        )
+        .route("/projects/:name/canary", post(projects::handle_canary))
+        .route("/projects/:name/disable", post(projects::handle_disable))
+        .route("/projects/:name/enable", post(projects::handle_enable))
         .route("/new", get(projects::new))
+        .route("/new/blank", get(projects::handle_new_blank))
         .route("/new/:name", get(projects::handle_new))
         .route("/settings", get(settings::index))
         .route("/settings/tokens/create", post(settings::create_token))
         .route("/settings/tokens/remove", post(settings::remove_token))
         .route("/traffic/requests", post(traffic::requests))
         .route("/traffic/flows", post(traffic::flows))
+        .route("/traffic/cpu", post(traffic::cpu))
+        .route("/traffic/latency", post(traffic::latency))
         .route("/traffic/projects", post(traffic::projects))
         .nest_service("/static", ServeDir::new(static_assets_dir))
         .route_layer(middleware::from_fn(middle::auth))
@@ -3,14 +3,26 @@ use crate::{
     dash::{error_html, notfound_html},
     templates::{Engine, RenderHtmlMinified},
 };
-use axum::{extract::Path, http::StatusCode, response::IntoResponse, Extension, Form, Json};
+use axum::{
+    body::Bytes,
+    extract::{Path, Query},
+    http::StatusCode,
+    response::{
+        sse::{Event, KeepAlive},
+        IntoResponse, Sse,
+    },
+    Extension, Form, Json,
+};
 use axum_htmx::HxRedirect;
 use htmlentity::entity::{encode, CharacterSet, EncodeType, ICodedDataTrait};
-use land_core::examples::{self, Item};
-use land_dao::{deploys, projects, settings};
-use land_vars::{AuthUser, BreadCrumbKey, Page, Project};
+use land_core::{
+    agent, deployer,
+    examples::{self, Item},
+};
+use land_dao::{deploys, envs, models::deployment, project_logs, projects, settings};
+use land_vars::{AuthUser, BreadCrumbKey, Deployment, Env, LogLine, Page, Project};
 use serde::{Deserialize, Serialize};
-use std::str::FromStr;
+use std::{convert::Infallible, str::FromStr};
 use tracing::{info, warn};
 
 /// index is handler for projects index page, /projects
@@ -83,12 +95,13 @@ pub async fn handle_new(
     )
     .await?;
     let dp = deploys::create(
-        user.id,
+        &project,
         user.uuid,
-        project.id,
-        project.uuid,
-        project.prod_domain,
+        project.prod_domain.clone(),
         deploys::DeployType::Production,
+        playground.id,
+        &deploys::Spec::default(),
+        deploys::Status::WaitDeploy,
     )
     .await?;
     info!(
@@ -102,6 +115,20 @@ pub async fn handle_new(
     Ok(redirect(format!("/projects/{}", project.name).as_str()))
 }
 
+/// handle_new_blank is handler for creating blank project, /new/blank
+/// blank project deploys uploaded wasm component
+pub async fn handle_new_blank(
+    Extension(user): Extension<AuthUser>,
+) -> Result<impl IntoResponse, ServerError> {
+    let project = projects::create_blank(user.id, "a blank project".to_string()).await?;
+    info!(
+        owner_id = user.id,
+        project_name = project.name,
+        "Create new blank project",
+    );
+    Ok(redirect(format!("/projects/{}", project.name).as_str()))
+}
+
 /// single is handler for projects single page, /projects/:name
 pub async fn single(
     engine: Engine,
@@ -113,6 +140,7 @@ pub async fn single(
         pub page: Page,
         pub project_name: String,
         pub project: Project,
+        pub deployments: Vec<Deployment>,
     }
     let project = projects::get_by_name(&name, Some(user.id)).await?;
     if project.is_none() {
@@ -120,6 +148,7 @@ pub async fn single(
         return Ok(notfound_html(engine, &msg, user).into_response());
     }
     let project = Project::new_with_source(&project.unwrap()).await?;
+    let deployments = deploys::list_by_project(project.id, 10).await?;
     Ok(RenderHtmlMinified(
         "project-single.hbs",
         engine,
@@ -127,6 +156,7 @@ pub async fn single(
             page: Page::new(&name, BreadCrumbKey::ProjectSingle, Some(user)),
             project_name: name,
             project,
+            deployments: Deployment::new_with_workers(deployments).await?,
         },
     )
     .into_response())
@@ -162,6 +192,166 @@ pub async fn traffic(
     .into_response())
 }
 
+/// logs is handler for project logs page, /projects/:name/logs
+pub async fn logs(
+    engine: Engine,
+    Extension(user): Extension<AuthUser>,
+    Path(name): Path<String>,
+) -> Result<impl IntoResponse, ServerError> {
+    #[derive(Serialize)]
+    struct Vars {
+        pub page: Page,
+        pub project_name: String,
+        pub project: Project,
+        pub retention: i64,
+    }
+    let project = projects::get_by_name(&name, Some(user.id)).await?;
+    if project.is_none() {
+        let msg = format!("Project {} not found", name);
+        return Ok(notfound_html(engine, &msg, user).into_response());
+    }
+    let project = Project::new_with_source(&project.unwrap()).await?;
+    Ok(RenderHtmlMinified(
+        "project-logs.hbs",
+        engine,
+        Vars {
+            page: Page::new(&name, BreadCrumbKey::ProjectLogs, Some(user)),
+            project_name: name,
+            project,
+            retention: agent::logs_retention(),
+        },
+    )
+    .into_response())
+}
+
+/// logs_tail is handler for live tailing project logs as server-sent events,
+/// /projects/:name/logs/tail
+pub async fn logs_tail(
+    Extension(user): Extension<AuthUser>,
+    Path(name): Path<String>,
+) -> Result<impl IntoResponse, ServerError> {
+    let project = projects::get_by_name(&name, Some(user.id)).await?;
+    if project.is_none() {
+        return Ok(error_html("Project not found").into_response());
+    }
+    let project_id = project.unwrap().id;
+    // send latest lines first, then new lines every second
+    let stream = futures_util::stream::unfold(None, move |last_id: Option<i32>| async move {
+        if last_id.is_some() {
+            tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+        }
+        let limit = if last_id.is_some() { 500 } else { 200 };
+        let models = match project_logs::list(project_id, last_id, limit).await {
+            Ok(v) => v,
+            Err(e) => {
+                warn!(project_id, "List logs failed: {:?}", e);
+                let event = Event::default().comment("error");
+                return Some((Ok::<Event, Infallible>(event), last_id.or(Some(0))));
+            }
+        };
+        let next_id = models.last().map(|m| m.id).or(last_id).or(Some(0));
+        if models.is_empty() {
+            return Some((Ok(Event::default().comment("waiting")), next_id));
+        }
+        let lines = LogLine::new_from_models(models);
+        let data = serde_json::to_string(&lines).unwrap_or_default();
+        Some((Ok(Event::default().event("log").data(data)), next_id))
+    });
+    Ok(Sse::new(stream)
+        .keep_alive(KeepAlive::default())
+        .into_response())
+}
+
+/// inspect is handler for project request inspector page, /projects/:name/inspect
+pub async fn inspect(
+    engine: Engine,
+    Extension(user): Extension<AuthUser>,
+    Path(name): Path<String>,
+) -> Result<impl IntoResponse, ServerError> {
+    #[derive(Serialize)]
+    struct Vars {
+        pub page: Page,
+        pub project_name: String,
+        pub project: Project,
+    }
+    let project = projects::get_by_name(&name, Some(user.id)).await?;
+    if project.is_none() {
+        let msg = format!("Project {} not found", name);
+        return Ok(notfound_html(engine, &msg, user).into_response());
+    }
+    let project = Project::new_with_source(&project.unwrap()).await?;
+    Ok(RenderHtmlMinified(
+        "project-inspect.hbs",
+        engine,
+        Vars {
+            page: Page::new(&name, BreadCrumbKey::ProjectInspect, Some(user)),
+            project_name: name,
+            project,
+        },
+    )
+    .into_response())
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ProjectInspectForm {
+    pub enabled: bool,
+}
+
+/// handle_inspect is handler for turning on or off request inspector, /projects/:name/inspect
+pub async fn handle_inspect(
+    Extension(user): Extension<AuthUser>,
+    Path(name): Path<String>,
+    Form(f): Form<ProjectInspectForm>,
+) -> Result<impl IntoResponse, ServerError> {
+    let project = projects::get_by_name(&name, Some(user.id)).await?;
+    if project.is_none() {
+        return Ok(error_html("Project not found").into_response());
+    }
+    let project_id = project.unwrap().id;
+    projects::set_inspect(project_id, f.enabled).await?;
+    if !f.enabled {
+        agent::clear_inspect(project_id);
+    }
+    info!(
+        owner_id = user.id,
+        project_name = name,
+        enabled = f.enabled,
+        "Set inspect"
+    );
+    let uri = axum::http::Uri::from_str(format!("/projects/{}/inspect", name).as_str())?;
+    Ok((HxRedirect(uri), ()).into_response())
+}
+
+/// inspect_tail is handler for streaming sampled requests as server-sent events,
+/// /projects/:name/inspect/tail
+pub async fn inspect_tail(
+    Extension(user): Extension<AuthUser>,
+    Path(name): Path<String>,
+) -> Result<impl IntoResponse, ServerError> {
+    let project = projects::get_by_name(&name, Some(user.id)).await?;
+    if project.is_none() {
+        return Ok(error_html("Project not found").into_response());
+    }
+    let project_id = project.unwrap().id;
+    // send kept requests first, then new requests every second
+    let stream = futures_util::stream::unfold(None, move |last_seq: Option<u64>| async move {
+        if last_seq.is_some() {
+            tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+        }
+        let items = agent::list_inspect(project_id, last_seq.unwrap_or_default());
+        let next_seq = items.last().map(|item| item.seq).or(last_seq).or(Some(0));
+        if items.is_empty() {
+            let event = Event::default().comment("waiting");
+            return Some((Ok::<Event, Infallible>(event), next_seq));
+        }
+        let data = serde_json::to_string(&items).unwrap_or_default();
+        Some((Ok(Event::default().event("request").data(data)), next_seq))
+    });
+    Ok(Sse::new(stream)
+        .keep_alive(KeepAlive::default())
+        .into_response())
+}
+
 /// settings is handler for projects settings page, /projects/:name/settings
 pub async fn settings(
     engine: Engine,
@@ -174,6 +364,7 @@ pub async fn settings(
         pub project_name: String,
         pub project: Project,
         pub domain: String,
+        pub envs: Vec<Env>,
     }
     let project = projects::get_by_name(&name, Some(user.id)).await?;
     if project.is_none() {
@@ -182,6 +373,7 @@ pub async fn settings(
     }
     let domain_settings = settings::get_domain_settings().await?;
     let project = Project::new_with_source(&project.unwrap()).await?;
+    let envs = envs::list_by_project(project.id).await?;
     Ok(RenderHtmlMinified(
         "project-settings.hbs",
         engine,
@@ -190,6 +382,7 @@ pub async fn settings(
             project_name: name,
             project,
             domain: domain_settings.domain_suffix,
+            envs: Env::new_from_models(envs),
         },
     )
     .into_response())
@@ -231,6 +424,222 @@ pub async fn handle_settings(
     Ok((parts, ()).into_response())
 }
 
+/// is_valid_env_key checks env name is uppercase letters, digits and underscore, not starting with digit
+fn is_valid_env_key(key: &str) -> bool {
+    !key.is_empty()
+        && !key.starts_with(|c: char| c.is_ascii_digit())
+        && key
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+}
+
+/// parse_envs_form parses envs form pairs in order of env_key, env_value and env_secret
+fn parse_envs_form(pairs: Vec<(String, String)>) -> Result<Vec<envs::Item>, String> {
+    let mut items: Vec<envs::Item> = vec![];
+    for (k, v) in pairs {
+        if k == "env_key" {
+            items.push(envs::Item {
+                key: v.trim().to_string(),
+                ..Default::default()
+            });
+            continue;
+        }
+        let item = items.last_mut().ok_or("Invalid envs form")?;
+        if k == "env_value" {
+            item.value = v;
+        } else if k == "env_secret" {
+            item.is_secret = true;
+        }
+    }
+    // skip empty rows
+    items.retain(|item| !item.key.is_empty() || !item.value.is_empty());
+    let mut keys = vec![];
+    for item in items.iter() {
+        if !is_valid_env_key(&item.key) {
+            return Err(format!(
+                "Invalid env name: '{}', use uppercase letters, digits and underscore, not starting with digit",
+                item.key
+            ));
+        }
+        if keys.contains(&item.key) {
+            return Err(format!("Duplicated env name: '{}'", item.key));
+        }
+        keys.push(item.key.clone());
+    }
+    Ok(items)
+}
+
+/// handle_envs is handler for projects envs settings, /projects/:name/envs
+pub async fn handle_envs(
+    Extension(user): Extension<AuthUser>,
+    Path(name): Path<String>,
+    Form(f): Form<Vec<(String, String)>>,
+) -> Result<impl IntoResponse, ServerError> {
+    let project = projects::get_by_name(&name, Some(user.id)).await?;
+    if project.is_none() {
+        return Ok(error_html("Project not found").into_response());
+    }
+    let project = project.unwrap();
+    let items = match parse_envs_form(f) {
+        Ok(items) => items,
+        Err(msg) => return Ok(error_html(&msg).into_response()),
+    };
+    let count = items.len();
+    if let Err(e) = envs::update(user.id, project.id, items).await {
+        warn!(
+            owner_id = user.id,
+            project_name = name,
+            "Update envs failed: {}",
+            e
+        );
+        return Ok(error_html(&e.to_string()).into_response());
+    }
+    info!(
+        owner_id = user.id,
+        project_name = name,
+        "Update envs, count: {}",
+        count
+    );
+    let uri = axum::http::Uri::from_str(format!("/projects/{}/settings", name).as_str())?;
+    let parts = HxRedirect(uri);
+    Ok((parts, ()).into_response())
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ProjectRollbackForm {
+    pub deploy_id: i32,
+}
+
+/// handle_rollback is handler for rollback to a previous deployment, /projects/:name/rollback
+pub async fn handle_rollback(
+    Extension(user): Extension<AuthUser>,
+    Path(name): Path<String>,
+    Form(f): Form<ProjectRollbackForm>,
+) -> Result<impl IntoResponse, ServerError> {
+    let project = projects::get_by_name(&name, Some(user.id)).await?;
+    if project.is_none() {
+        return Ok(error_html("Project not found").into_response());
+    }
+    let dp = match deployer::rollback(project.unwrap().id, f.deploy_id).await {
+        Ok(dp) => dp,
+        Err(e) => {
+            warn!(
+                owner_id = user.id,
+                project_name = name,
+                "Rollback failed: {}",
+                e
+            );
+            return Ok(error_html(&e.to_string()).into_response());
+        }
+    };
+    info!(
+        owner_id = user.id,
+        project_name = name,
+        dp_id = dp.id,
+        "Rollback to deployment {}",
+        f.deploy_id
+    );
+    let uri = axum::http::Uri::from_str(format!("/projects/{}", name).as_str())?;
+    Ok((HxRedirect(uri), ()).into_response())
+}
+
+/// handle_promote is handler for promoting preview deployment to production, /projects/:name/promote
+pub async fn handle_promote(
+    Extension(user): Extension<AuthUser>,
+    Path(name): Path<String>,
+    Form(f): Form<ProjectRollbackForm>,
+) -> Result<impl IntoResponse, ServerError> {
+    let project = projects::get_by_name(&name, Some(user.id)).await?;
+    if project.is_none() {
+        return Ok(error_html("Project not found").into_response());
+    }
+    let dp = match deployer::promote(project.unwrap().id, f.deploy_id).await {
+        Ok(dp) => dp,
+        Err(e) => {
+            warn!(
+                owner_id = user.id,
+                project_name = name,
+                "Promote failed: {}",
+                e
+            );
+            return Ok(error_html(&e.to_string()).into_response());
+        }
+    };
+    info!(
+        owner_id = user.id,
+        project_name = name,
+        dp_id = dp.id,
+        "Promote deployment {}",
+        f.deploy_id
+    );
+    let uri = axum::http::Uri::from_str(format!("/projects/{}", name).as_str())?;
+    Ok((HxRedirect(uri), ()).into_response())
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ProjectCanaryForm {
+    pub weight: i32,
+}
+
+/// handle_canary is handler for splitting traffic to preview deployment, /projects/:name/canary
+pub async fn handle_canary(
+    Extension(user): Extension<AuthUser>,
+    Path(name): Path<String>,
+    Form(f): Form<ProjectCanaryForm>,
+) -> Result<impl IntoResponse, ServerError> {
+    let project = projects::get_by_name(&name, Some(user.id)).await?;
+    if project.is_none() {
+        return Ok(error_html("Project not found").into_response());
+    }
+    if let Err(e) = deployer::set_canary(project.unwrap().id, f.weight).await {
+        warn!(
+            owner_id = user.id,
+            project_name = name,
+            "Set canary failed: {}",
+            e
+        );
+        return Ok(error_html(&e.to_string()).into_response());
+    }
+    info!(
+        owner_id = user.id,
+        project_name = name,
+        weight = f.weight,
+        "Set canary"
+    );
+    let uri = axum::http::Uri::from_str(format!("/projects/{}/settings", name).as_str())?;
+    Ok((HxRedirect(uri), ()).into_response())
+}
+
+/// handle_disable is handler for disabling project, /projects/:name/disable
+pub async fn handle_disable(
+    Extension(user): Extension<AuthUser>,
+    Path(name): Path<String>,
+) -> Result<impl IntoResponse, ServerError> {
+    let project = projects::get_by_name(&name, Some(user.id)).await?;
+    if project.is_none() {
+        return Ok(error_html("Project not found").into_response());
+    }
+    deployer::disable_project(project.unwrap().id).await?;
+    info!(owner_id = user.id, project_name = name, "Disable project");
+    let uri = axum::http::Uri::from_str(format!("/projects/{}/settings", name).as_str())?;
+    Ok((HxRedirect(uri), ()).into_response())
+}
+
+/// handle_enable is handler for enabling project, /projects/:name/enable
+pub async fn handle_enable(
+    Extension(user): Extension<AuthUser>,
+    Path(name): Path<String>,
+) -> Result<impl IntoResponse, ServerError> {
+    let project = projects::get_by_name(&name, Some(user.id)).await?;
+    if project.is_none() {
+        return Ok(error_html("Project not found").into_response());
+    }
+    deployer::enable_project(project.unwrap().id).await?;
+    info!(owner_id = user.id, project_name = name, "Enable project");
+    let uri = axum::http::Uri::from_str(format!("/projects/{}/settings", name).as_str())?;
+    Ok((HxRedirect(uri), ()).into_response())
+}
+
 /// edit is handler for projects eidt page, /projects/:name/edit
 pub async fn edit(
     engine: Engine,
@@ -249,6 +658,10 @@ pub async fn edit(
         return Ok(notfound_html(engine, &msg, user).into_response());
     }
     let project = Project::new_with_source(&project.unwrap()).await?;
+    if !project.is_editable {
+        let msg = format!("Project {} is not editable", name);
+        return Ok(notfound_html(engine, &msg, user).into_response());
+    }
     Ok(RenderHtmlMinified(
         "project-edit.hbs",
         engine,
@@ -270,6 +683,21 @@ pub struct ProjectEditForm {
 struct ProjectEditResp {
     pub task_id: String,
     pub deploy_id: i32,
+    pub url: String,
+}
+
+impl ProjectEditResp {
+    async fn new(dp: deployment::Model) -> anyhow::Result<Self> {
+        let domain_settings = settings::get_domain_settings().await?;
+        Ok(Self {
+            url: format!(
+                "{}://{}.{}",
+                domain_settings.http_protocol, dp.domain, domain_settings.domain_suffix
+            ),
+            task_id: dp.task_id,
+            deploy_id: dp.id,
+        })
+    }
 }
 
 /// handle_edit is handler for projects edit page, /projects/:name/edit
@@ -283,13 +711,75 @@ pub async fn handle_edit(
         return Ok(error_html("Project not found").into_response());
     }
     let project = project.unwrap();
-    let dp = projects::update_source(project.id, f.source).await?;
+    let dp = projects::update_source(project.id, f.source, deploys::DeployType::Production).await?;
     info!(owner_id = user.id, project_name = name, "Edit project");
-    Ok(Json(ProjectEditResp {
-        task_id: dp.task_id,
-        deploy_id: dp.id,
-    })
-    .into_response())
+    Ok(Json(ProjectEditResp::new(dp).await?).into_response())
+}
+
+/// handle_preview is handler for deploying source to preview domain, /projects/:name/preview
+pub async fn handle_preview(
+    Extension(user): Extension<AuthUser>,
+    Path(name): Path<String>,
+    Form(f): Form<ProjectEditForm>,
+) -> Result<impl IntoResponse, ServerError> {
+    let project = projects::get_by_name(&name, Some(user.id)).await?;
+    if project.is_none() {
+        return Ok(error_html("Project not found").into_response());
+    }
+    let project = project.unwrap();
+    let dp =
+        projects::update_source(project.id, f.source, deploys::DeployType::Development).await?;
+    info!(
+        owner_id = user.id,
+        project_name = name,
+        dp_id = dp.id,
+        "Preview project"
+    );
+    Ok(Json(ProjectEditResp::new(dp).await?).into_response())
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ProjectUploadQuery {
+    #[serde(default)]
+    pub preview: bool,
+}
+
+/// handle_upload is handler for deploying uploaded wasm component, /projects/:name/upload
+pub async fn handle_upload(
+    Extension(user): Extension<AuthUser>,
+    Path(name): Path<String>,
+    Query(q): Query<ProjectUploadQuery>,
+    body: Bytes,
+) -> Result<impl IntoResponse, ServerError> {
+    let project = projects::get_by_name(&name, Some(user.id)).await?;
+    if project.is_none() {
+        return Ok((StatusCode::NOT_FOUND, error_html("Project not found")).into_response());
+    }
+    let deploy_type = if q.preview {
+        deploys::DeployType::Development
+    } else {
+        deploys::DeployType::Production
+    };
+    let dp = match deployer::upload(project.unwrap().id, body.to_vec(), deploy_type).await {
+        Ok(dp) => dp,
+        Err(e) => {
+            warn!(
+                owner_id = user.id,
+                project_name = name,
+                "Upload failed: {}",
+                e
+            );
+            return Ok((StatusCode::BAD_REQUEST, error_html(&e.to_string())).into_response());
+        }
+    };
+    info!(
+        owner_id = user.id,
+        project_name = name,
+        dp_id = dp.id,
+        size = body.len(),
+        "Upload project"
+    );
+    Ok(Json(ProjectEditResp::new(dp).await?).into_response())
 }
 
 #[derive(Deserialize, Debug)]
@@ -332,3 +822,48 @@ pub async fn handle_status(
     })
     .into_response())
 }
+
+/// build_logs is handler for streaming build logs of a deployment as server-sent events,
+/// /projects/:name/deployments/:deploy_id/logs
+pub async fn build_logs(
+    Extension(user): Extension<AuthUser>,
+    Path((name, deploy_id)): Path<(String, i32)>,
+) -> Result<impl IntoResponse, ServerError> {
+    let project = projects::get_by_name(&name, Some(user.id)).await?;
+    if project.is_none() {
+        return Ok(error_html("Project not found").into_response());
+    }
+    let project_id = project.unwrap().id;
+    let dp = deploys::get_by_id(deploy_id).await?;
+    if dp.filter(|dp| dp.project_id == project_id).is_none() {
+        return Ok(error_html("Deployment not found").into_response());
+    }
+    // send new log content every 500ms until deployment is finished
+    let stream = futures_util::stream::unfold(Some((0usize, true)), move |state| async move {
+        let (offset, first) = state?;
+        if !first {
+            tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+        }
+        let (content, running) = match deployer::get_log(deploy_id).await {
+            Ok(v) => v,
+            Err(e) => {
+                let event = Event::default().event("error").data(e.to_string());
+                return Some((Ok::<Event, Infallible>(event), None));
+            }
+        };
+        let data = content.get(offset..).unwrap_or_default();
+        if !running {
+            let event = Event::default().event("done").data(data);
+            return Some((Ok(event), None));
+        }
+        let event = if data.is_empty() {
+            Event::default().comment("waiting")
+        } else {
+            Event::default().event("log").data(data)
+        };
+        Some((Ok(event), Some((content.len(), false))))
+    });
+    Ok(Sse::new(stream)
+        .keep_alive(KeepAlive::default())
+        .into_response())
+}
@@ -42,6 +42,44 @@ pub async fn flows(
     Ok(Json(lines))
 }
 
+/// cpu is route of traffic cpu time query, /traffic/cpu
+pub async fn cpu(
+    Extension(user): Extension<AuthUser>,
+    Form(f): Form<traffic::Form>,
+) -> Result<impl IntoResponse, ServerError> {
+    let now = tokio::time::Instant::now();
+    let pid = f.pid.map(|pid| pid.to_string());
+    let period = traffic::PeriodParams::new(&f.period, None);
+    let lines = traffic::cpu_traffic(pid, Some(user.id.to_string()), &period).await?;
+    info!(
+        "cpu, start:{}, end:{}, step:{}, cost:{}",
+        period.start,
+        period.end,
+        period.step,
+        now.elapsed().as_millis(),
+    );
+    Ok(Json(lines))
+}
+
+/// latency is route of traffic latency percentiles query, /traffic/latency
+pub async fn latency(
+    Extension(user): Extension<AuthUser>,
+    Form(f): Form<traffic::Form>,
+) -> Result<impl IntoResponse, ServerError> {
+    let now = tokio::time::Instant::now();
+    let pid = f.pid.map(|pid| pid.to_string());
+    let period = traffic::PeriodParams::new(&f.period, None);
+    let lines = traffic::latency_traffic(pid, Some(user.id.to_string()), &period).await?;
+    info!(
+        "latency, start:{}, end:{}, step:{}, cost:{}",
+        period.start,
+        period.end,
+        period.step,
+        now.elapsed().as_millis(),
+    );
+    Ok(Json(lines))
+}
+
 /// flows is route of traffic requests query page, /traffic/flows
 pub async fn projects(
     Extension(user): Extension<AuthUser>,
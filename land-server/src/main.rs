@@ -31,6 +31,18 @@ struct Args {
     /// Database connection args.
     #[clap(flatten)]
     dbargs: land_dao::DBArgs,
+    /// Secret to encrypt project secret envs. If empty, secret envs are disabled.
+    #[clap(long, env = "LAND_ENVS_SECRET", default_value = "")]
+    envs_secret: String,
+    /// Max number of deployments building at the same time.
+    #[clap(long, env = "LAND_BUILD_CONCURRENCY", default_value("2"))]
+    build_concurrency: usize,
+    /// Timeout seconds of building a deployment, the build process is killed after timeout.
+    #[clap(long, env = "LAND_BUILD_TIMEOUT", default_value("300"))]
+    build_timeout: u64,
+    /// Hours to keep guest logs of projects, expired logs are deleted.
+    #[clap(long, env = "LAND_LOGS_RETENTION", default_value("72"))]
+    logs_retention: i64,
 }
 
 #[tokio::main]
@@ -48,6 +60,7 @@ async fn main() -> anyhow::Result<()> {
     land_dao::connect(&args.dbargs)
         .await
         .expect("Failed to connect to database");
+    land_dao::envs::init_secret(&args.envs_secret).expect("Failed to initialize envs secret");
 
     // Clerk env initialize
     clerk::init().await.expect("Failed to initialize clerk env");
@@ -65,10 +78,13 @@ async fn main() -> anyhow::Result<()> {
         // Initialize living agent refreshing
         agent::init_livings().await;
         agent::init_confs().await;
+        agent::init_logs_retention(args.logs_retention).await;
 
         // Initialize handling waiting and reviewing deployments
-        deployer::init_waiting().await;
+        deployer::init_waiting(args.build_concurrency, args.build_timeout).await;
         deployer::init_review().await;
+        deployer::init_dispatch().await;
+        deployer::init_watchdog().await;
     }
 
     // Start server
@@ -0,0 +1,21 @@
+use super::{response_ok, JsonError};
+use axum::{extract::Query, response::IntoResponse, Json};
+use land_core::agent::{self, InspectRecord};
+use serde::Deserialize;
+use tracing::debug;
+
+#[derive(Deserialize, Debug)]
+pub struct IPQuery {
+    ip: String,
+}
+
+/// handle /worker-api/inspect
+pub async fn handle(
+    Query(q): Query<IPQuery>,
+    Json(records): Json<Vec<InspectRecord>>,
+) -> Result<impl IntoResponse, JsonError> {
+    let count = records.len();
+    agent::save_inspect(records);
+    debug!(ip = q.ip, "Save inspect records: {}", count);
+    Ok(response_ok(count, None))
+}
@@ -0,0 +1,20 @@
+use super::{response_ok, JsonError};
+use axum::{extract::Query, response::IntoResponse, Json};
+use land_dao::project_logs;
+use serde::Deserialize;
+use tracing::debug;
+
+#[derive(Deserialize, Debug)]
+pub struct IPQuery {
+    ip: String,
+}
+
+/// handle /worker-api/logs
+pub async fn handle(
+    Query(q): Query<IPQuery>,
+    Json(items): Json<Vec<project_logs::Item>>,
+) -> Result<impl IntoResponse, JsonError> {
+    let count = project_logs::create_batch(&q.ip, items).await?;
+    debug!(ip = q.ip, "Save logs: {}", count);
+    Ok(response_ok(count, None))
+}
@@ -1,5 +1,6 @@
 use anyhow::Result;
 use axum::{
+    extract::DefaultBodyLimit,
     http::StatusCode,
     middleware,
     response::{Html, IntoResponse},
@@ -8,10 +9,15 @@ use axum::{
 };
 use serde::{Deserialize, Serialize};
 
+mod inspect;
+mod logs;
 mod middle;
 mod sync;
 mod task;
 
+/// MAX_LOGS_BODY_SIZE is max body size of shipped logs or inspect records batch, 8MB
+const MAX_LOGS_BODY_SIZE: usize = 8 * 1024 * 1024;
+
 async fn handler() -> impl IntoResponse {
     Html("Hello World - Worker API !")
 }
@@ -21,6 +27,14 @@ pub async fn route() -> Result<Router> {
         .route("/", get(handler))
         .route("/sync", post(sync::handle))
         .route("/task", post(task::handle))
+        .route(
+            "/inspect",
+            post(inspect::handle).layer(DefaultBodyLimit::max(MAX_LOGS_BODY_SIZE)),
+        )
+        .route(
+            "/logs",
+            post(logs::handle).layer(DefaultBodyLimit::max(MAX_LOGS_BODY_SIZE)),
+        )
         .route_layer(middleware::from_fn(middle::auth));
     Ok(app)
 }
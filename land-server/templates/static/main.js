@@ -316,6 +316,165 @@ document.addEventListener("DOMContentLoaded", function () {
         }
         flow_traffic(pid);
 
+        async function cpu_traffic(pid) {
+            let c = document.getElementById('cpu-chart');
+            if (!c) {
+                return;
+            }
+            let params = new URLSearchParams();
+            params.append("period", window.traffic_period);
+            if (pid) {
+                params.append("pid", pid);
+            }
+            let resp = await fetch(`/traffic/cpu`, {
+                method: "POST",
+                body: params,
+            })
+            let data = await resp.json();
+
+            c.style.height = "200px";
+            c.style.width = "100%";
+
+            let loading = document.getElementById('cpu-chart-loading');
+            loading.style.display = "none";
+
+            const total_div = document.getElementById("cpu-total");
+            total_div.classList.remove("d-none");
+            document.getElementById('cpu-total-value').innerText = (data['metric']?.total || 0) + " ms";
+
+            let chart = echarts.init(c, null);
+            let option = {
+                title: {
+                    show: false,
+                },
+                tooltip: {
+                    trigger: "axis",
+                },
+                xAxis: {
+                    type: 'time',
+                    axisLabel: {
+                        formatter: function (value, index) {
+                            return unix2hour(parseInt(value)) // js use milliseconds
+                        },
+                    },
+                    splitNumber: 3,
+                },
+                yAxis: {
+                    show: false
+                },
+                grid: {
+                    top: 0,
+                    left: 0,
+                    right: 0,
+                    bottom: 20,
+                },
+                series: [
+                    {
+                        type: 'line',
+                        data: data["metric"]?.values || [],
+                        name: "cpu ms",
+                        smooth: true,
+                        symbol: "none",
+                    },
+                ],
+                color: ['#ee6666'],
+            }
+            chart.setOption(option);
+        }
+        cpu_traffic(pid);
+
+        // average of non-zero values, zero means no requests in the step
+        function avg_values(values) {
+            let points = (values || []).filter((v) => v[1] > 0);
+            if (points.length == 0) {
+                return 0;
+            }
+            return Math.round(points.reduce((sum, v) => sum + v[1], 0) / points.length);
+        }
+
+        async function latency_traffic(pid) {
+            let c = document.getElementById('latency-chart');
+            if (!c) {
+                return;
+            }
+            let params = new URLSearchParams();
+            params.append("period", window.traffic_period);
+            if (pid) {
+                params.append("pid", pid);
+            }
+            let resp = await fetch(`/traffic/latency`, {
+                method: "POST",
+                body: params,
+            })
+            let data = await resp.json();
+
+            c.style.height = "200px";
+            c.style.width = "100%";
+
+            let loading = document.getElementById('latency-chart-loading');
+            loading.style.display = "none";
+
+            const total_div = document.getElementById("latency-total");
+            total_div.classList.remove("d-none");
+            document.getElementById('latency-total-p50').innerText = avg_values(data['p50']?.values) + " ms";
+            document.getElementById('latency-total-p99').innerText = avg_values(data['p99']?.values) + " ms";
+            document.getElementById('latency-total-cold').innerText = data['typ-cold']?.total || 0;
+
+            let chart = echarts.init(c, null);
+            let option = {
+                title: {
+                    show: false,
+                },
+                tooltip: {
+                    trigger: "axis",
+                },
+                xAxis: {
+                    type: 'time',
+                    axisLabel: {
+                        formatter: function (value, index) {
+                            return unix2hour(parseInt(value)) // js use milliseconds
+                        },
+                    },
+                    splitNumber: 3,
+                },
+                yAxis: {
+                    show: false
+                },
+                grid: {
+                    top: 0,
+                    left: 0,
+                    right: 0,
+                    bottom: 20,
+                },
+                series: [
+                    {
+                        type: 'line',
+                        data: data["p50"]?.values || [],
+                        name: "p50 ms",
+                        smooth: true,
+                        symbol: "none",
+                    },
+                    {
+                        type: 'line',
+                        data: data["p95"]?.values || [],
+                        name: "p95 ms",
+                        smooth: true,
+                        symbol: "none",
+                    },
+                    {
+                        type: 'line',
+                        data: data["p99"]?.values || [],
+                        name: "p99 ms",
+                        smooth: true,
+                        symbol: "none",
+                    },
+                ],
+                color: ['#5470c6', '#fac858', '#B03A5B'],
+            }
+            chart.setOption(option);
+        }
+        latency_traffic(pid);
+
         document.querySelectorAll(".traffic-period-select").forEach((el) => {
             el.addEventListener("click", function () {
                 let period = el.getAttribute("x-data-v");
@@ -329,6 +488,8 @@ document.addEventListener("DOMContentLoaded", function () {
 
                 request_traffic(pid);
                 flow_traffic(pid);
+                cpu_traffic(pid);
+                latency_traffic(pid);
             })
         });
     })();
@@ -2,6 +2,7 @@ use anyhow::Result;
 use clap::Parser;
 use land_common::{logging, version};
 use land_core::agent;
+use tracing::warn;
 
 #[derive(Parser, Debug)]
 #[clap(author, version)]
@@ -48,6 +49,75 @@ struct Args {
         default_value("0.0.0.0:9000")
     )]
     pub metrics_addr: String,
+    /// Hosts or CIDRs that outbound fetch is allowed to, comma separated. Empty means no limit.
+    #[clap(
+        long = "egress-allow",
+        env = "LAND_EGRESS_ALLOW",
+        value_delimiter = ','
+    )]
+    pub egress_allow: Vec<String>,
+    /// Hosts or CIDRs that outbound fetch is denied to, comma separated.
+    #[clap(long = "egress-deny", env = "LAND_EGRESS_DENY", value_delimiter = ',')]
+    pub egress_deny: Vec<String>,
+    /// Allow outbound fetch to private, loopback and link-local addresses
+    #[clap(long = "egress-allow-private", env = "LAND_EGRESS_ALLOW_PRIVATE")]
+    pub egress_allow_private: bool,
+    /// Max count of cached workers, least recently used workers are evicted
+    #[clap(long = "max-workers", env = "LAND_MAX_WORKERS", default_value("1000"))]
+    pub max_workers: u64,
+    /// Instance allocator of wasmtime engine, pooling or ondemand
+    #[clap(
+        long = "engine-allocator",
+        env = "LAND_ENGINE_ALLOCATOR",
+        default_value("pooling"),
+        value_parser = ["pooling", "ondemand"]
+    )]
+    pub engine_allocator: String,
+    /// Max count of concurrent component instances in pooling allocator
+    #[clap(
+        long = "engine-instances",
+        env = "LAND_ENGINE_INSTANCES",
+        default_value("100")
+    )]
+    pub engine_instances: u32,
+    /// Max count of core instances in pooling allocator, a component instance needs several of them
+    #[clap(
+        long = "engine-core-instances",
+        env = "LAND_ENGINE_CORE_INSTANCES",
+        default_value("400")
+    )]
+    pub engine_core_instances: u32,
+    /// Max count of memories in pooling allocator
+    #[clap(
+        long = "engine-memories",
+        env = "LAND_ENGINE_MEMORIES",
+        default_value("100")
+    )]
+    pub engine_memories: u32,
+    /// Max linear memory size in MB of an instance in pooling allocator
+    #[clap(
+        long = "engine-max-memory",
+        env = "LAND_ENGINE_MAX_MEMORY",
+        default_value("128")
+    )]
+    pub engine_max_memory: usize,
+    /// Max elements of a table in pooling allocator
+    #[clap(
+        long = "engine-table-elements",
+        env = "LAND_ENGINE_TABLE_ELEMENTS",
+        default_value("98765")
+    )]
+    pub engine_table_elements: u32,
+    /// Max unused slots to keep warm in pooling allocator
+    #[clap(
+        long = "engine-warm-slots",
+        env = "LAND_ENGINE_WARM_SLOTS",
+        default_value("10")
+    )]
+    pub engine_warm_slots: u32,
+    /// Route requests by host header with synced deployments, no need traefik proxy
+    #[clap(long = "self-routing", env = "LAND_SELF_ROUTING")]
+    pub self_routing: bool,
 }
 
 fn validate_url(url: &str) -> Result<String, String> {
@@ -66,6 +136,44 @@ async fn main() -> Result<()> {
     // Initialize tracing
     logging::init(args.output.verbose);
 
+    // Initialize worker server
+    let secret = agent::init_secret(&args.dir)?;
+    let egress_policy = land_wasm_host::egress::Policy::new(
+        &args.egress_allow,
+        &args.egress_deny,
+        args.egress_allow_private,
+    )?;
+    let opts = land_wasm_server::Opts {
+        addr: args.address.parse().unwrap(),
+        dir: args.dir.clone(),
+        default_wasm: None,
+        enable_wasmtime_aot: true,
+        endpoint_name: args.hostname,
+        enable_metrics: true,
+        metrics_addr: Some(args.metrics_addr),
+        egress_policy,
+        routing_secret: Some(secret.clone()),
+        aot_secret: Some(secret),
+        max_workers: args.max_workers,
+        engine: land_wasm_host::EngineOpts {
+            pooling: args.engine_allocator == "pooling",
+            total_instances: args.engine_instances,
+            total_core_instances: args.engine_core_instances,
+            total_memories: args.engine_memories,
+            max_memory_mb: args.engine_max_memory,
+            table_elements: args.engine_table_elements,
+            max_unused_warm_slots: args.engine_warm_slots,
+        },
+        self_routing: args.self_routing,
+    };
+    land_wasm_server::init(&opts).await?;
+
+    // Load last synced confs, serve deployments even if server is unreachable.
+    // Workers are warmed in background, so server binds without waiting for them
+    if let Err(e) = agent::init_from_confs(&args.dir, &args.service_name).await {
+        warn!("Load confs error: {:?}", e);
+    }
+
     // Initialize agent role
     agent::init_ip(args.ip).await?;
     agent::init_sync(
@@ -81,18 +189,11 @@ async fn main() -> Result<()> {
         args.service_name.clone(),
     )
     .await;
+    agent::init_logs(args.server_url.clone(), args.token.clone()).await;
+    agent::init_inspect(args.server_url.clone(), args.token.clone()).await;
 
     // Start server
-    let opts = land_wasm_server::Opts {
-        addr: args.address.parse().unwrap(),
-        dir: args.dir,
-        default_wasm: None,
-        enable_wasmtime_aot: true,
-        endpoint_name: args.hostname,
-        enable_metrics: true,
-        metrics_addr: Some(args.metrics_addr),
-    };
-    land_wasm_server::start(opts).await?;
+    land_wasm_server::serve(opts).await?;
 
     Ok(())
 }
@@ -0,0 +1,198 @@
+use anyhow::{anyhow, Result};
+use base64::{
+    engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+    Engine,
+};
+use ring::{
+    aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN},
+    digest, hkdf, hmac,
+    rand::{SecureRandom, SystemRandom},
+};
+use std::collections::BTreeMap;
+use std::hash::{Hash, Hasher};
+
+/// derive_key derives a 32 bytes key from secret string by sha256
+pub fn derive_key(secret: &str) -> [u8; 32] {
+    let hash = digest::digest(&digest::SHA256, secret.as_bytes());
+    let mut key = [0u8; 32];
+    key.copy_from_slice(hash.as_ref());
+    key
+}
+
+/// derive_subkey derives a key for the purpose of label from secret by hkdf-sha256,
+/// returns base64 encoded key. One secret is not used directly for different purposes.
+pub fn derive_subkey(secret: &str, label: &str) -> String {
+    let prk = hkdf::Salt::new(hkdf::HKDF_SHA256, &[]).extract(secret.as_bytes());
+    let info = [label.as_bytes()];
+    let okm = prk.expand(&info, hkdf::HKDF_SHA256).unwrap();
+    let mut key = [0u8; 32];
+    okm.fill(&mut key).unwrap();
+    URL_SAFE_NO_PAD.encode(key)
+}
+
+/// encrypt encrypts plain text with aes-256-gcm, returns base64 encoded nonce and cipher text
+pub fn encrypt(key: &[u8; 32], plain: &str) -> Result<(String, String)> {
+    let unbound = UnboundKey::new(&AES_256_GCM, key).map_err(|_| anyhow!("Invalid key"))?;
+    let key = LessSafeKey::new(unbound);
+    let mut nonce_bytes = [0u8; NONCE_LEN];
+    SystemRandom::new()
+        .fill(&mut nonce_bytes)
+        .map_err(|_| anyhow!("Generate nonce failed"))?;
+    let nonce = Nonce::assume_unique_for_key(nonce_bytes);
+    let mut data = plain.as_bytes().to_vec();
+    key.seal_in_place_append_tag(nonce, Aad::empty(), &mut data)
+        .map_err(|_| anyhow!("Encrypt failed"))?;
+    Ok((STANDARD.encode(nonce_bytes), STANDARD.encode(data)))
+}
+
+/// decrypt decrypts base64 encoded cipher text with nonce by aes-256-gcm
+pub fn decrypt(key: &[u8; 32], nonce: &str, cipher: &str) -> Result<String> {
+    let unbound = UnboundKey::new(&AES_256_GCM, key).map_err(|_| anyhow!("Invalid key"))?;
+    let key = LessSafeKey::new(unbound);
+    let nonce_bytes: [u8; NONCE_LEN] = STANDARD
+        .decode(nonce)?
+        .try_into()
+        .map_err(|_| anyhow!("Invalid nonce"))?;
+    let nonce = Nonce::assume_unique_for_key(nonce_bytes);
+    let mut data = STANDARD.decode(cipher)?;
+    let plain = key
+        .open_in_place(nonce, Aad::empty(), &mut data)
+        .map_err(|_| anyhow!("Decrypt failed"))?;
+    Ok(String::from_utf8(plain.to_vec())?)
+}
+
+/// SIGNATURE_HEADER is the header of the signature over x-land-* routing headers
+pub const SIGNATURE_HEADER: &str = "x-land-sig";
+
+/// signing_content joins sorted x-land-* headers except signature as the content to sign
+fn signing_content(headers: &BTreeMap<String, String>) -> String {
+    headers
+        .iter()
+        .filter(|(k, _)| k.starts_with("x-land-") && k.as_str() != SIGNATURE_HEADER)
+        .map(|(k, v)| format!("{}:{}\n", k, v))
+        .collect()
+}
+
+/// sign_headers signs x-land-* headers by hmac-sha256, returns base64 encoded signature
+pub fn sign_headers(secret: &str, headers: &BTreeMap<String, String>) -> String {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+    let tag = hmac::sign(&key, signing_content(headers).as_bytes());
+    URL_SAFE_NO_PAD.encode(tag.as_ref())
+}
+
+/// verify_headers verifies the signature of x-land-* headers
+pub fn verify_headers(secret: &str, headers: &BTreeMap<String, String>, signature: &str) -> bool {
+    let Ok(tag) = URL_SAFE_NO_PAD.decode(signature) else {
+        return false;
+    };
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+    hmac::verify(&key, signing_content(headers).as_bytes(), &tag).is_ok()
+}
+
+/// sha256_digest returns base64 encoded sha256 digest of data
+pub fn sha256_digest(data: &[u8]) -> String {
+    let hash = digest::digest(&digest::SHA256, data);
+    URL_SAFE_NO_PAD.encode(hash.as_ref())
+}
+
+/// Sha256Hasher feeds hashed values into sha256
+struct Sha256Hasher(digest::Context);
+
+impl Hasher for Sha256Hasher {
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.update(bytes);
+    }
+    fn finish(&self) -> u64 {
+        let hash = self.0.clone().finish();
+        u64::from_le_bytes(hash.as_ref()[..8].try_into().unwrap())
+    }
+}
+
+/// stable_hash returns hex encoded sha256 of hashed value.
+/// Unlike DefaultHasher, it is not limited to 64 bits. It relies on Hash impls of the value,
+/// so hashes are only comparable between binaries built by the same toolchain and dependencies
+pub fn stable_hash<T: Hash + ?Sized>(value: &T) -> String {
+    let mut hasher = Sha256Hasher(digest::Context::new(&digest::SHA256));
+    value.hash(&mut hasher);
+    let hash = hasher.0.finish();
+    hash.as_ref().iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// sign_bytes signs data by hmac-sha256, returns base64 encoded signature
+pub fn sign_bytes(secret: &str, data: &[u8]) -> String {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+    let tag = hmac::sign(&key, data);
+    URL_SAFE_NO_PAD.encode(tag.as_ref())
+}
+
+/// verify_bytes verifies the hmac-sha256 signature of data
+pub fn verify_bytes(secret: &str, data: &[u8], signature: &str) -> bool {
+    let Ok(tag) = URL_SAFE_NO_PAD.decode(signature) else {
+        return false;
+    };
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+    hmac::verify(&key, data, &tag).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers() -> BTreeMap<String, String> {
+        BTreeMap::from([
+            ("x-land-fid".to_string(), "deploy-1.wasm".to_string()),
+            ("x-land-pid".to_string(), "1".to_string()),
+            ("x-land-did".to_string(), "2".to_string()),
+            ("user-agent".to_string(), "test".to_string()),
+        ])
+    }
+
+    #[test]
+    fn test_sign_headers() {
+        let sig = sign_headers("secret", &headers());
+        assert!(verify_headers("secret", &headers(), &sig));
+        // wrong secret or bad signature
+        assert!(!verify_headers("other", &headers(), &sig));
+        assert!(!verify_headers("secret", &headers(), "not-base64!"));
+        // non routing headers and signature header are not signed
+        let mut h = headers();
+        h.insert("user-agent".to_string(), "changed".to_string());
+        h.insert(SIGNATURE_HEADER.to_string(), sig.clone());
+        assert!(verify_headers("secret", &h, &sig));
+    }
+
+    #[test]
+    fn test_verify_tampered_headers() {
+        let sig = sign_headers("secret", &headers());
+        // tampered value
+        let mut h = headers();
+        h.insert("x-land-fid".to_string(), "deploy-3.wasm".to_string());
+        assert!(!verify_headers("secret", &h, &sig));
+        // missing header
+        let mut h = headers();
+        h.remove("x-land-pid");
+        assert!(!verify_headers("secret", &h, &sig));
+        // added header
+        let mut h = headers();
+        h.insert("x-land-extra".to_string(), "1".to_string());
+        assert!(!verify_headers("secret", &h, &sig));
+        // swapped values between headers
+        let mut h = headers();
+        h.insert("x-land-pid".to_string(), "2".to_string());
+        h.insert("x-land-did".to_string(), "1".to_string());
+        assert!(!verify_headers("secret", &h, &sig));
+    }
+
+    #[test]
+    fn test_sign_headers_order() {
+        // headers are sorted by name, insertion order does not matter
+        let mut h = BTreeMap::new();
+        for (k, v) in headers().into_iter().rev() {
+            h.insert(k, v);
+        }
+        assert_eq!(
+            sign_headers("secret", &h),
+            sign_headers("secret", &headers())
+        );
+    }
+}
@@ -0,0 +1,68 @@
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashSet, VecDeque},
+    sync::{Mutex, RwLock},
+};
+use tracing::info;
+
+/// MAX_RECORDS is max count of sampled requests waiting to be shipped,
+/// oldest records are dropped if the ring buffer is full
+pub const MAX_RECORDS: usize = 100;
+
+/// Record is a sampled request of a deployment
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Record {
+    pub req_id: String,
+    pub project_id: i32,
+    pub deploy_id: i32,
+    pub timestamp: i64, // unix timestamp in milliseconds
+    pub method: String,
+    pub uri: String,
+    pub req_headers: Vec<(String, String)>,
+    pub req_body: String,
+    pub status: u16,
+    pub resp_headers: Vec<(String, String)>,
+    pub resp_body: String,
+    pub elapsed_ms: f64,
+    pub cpu_ms: u64,
+    /// error is the trap or limit error message if guest fails
+    pub error: Option<String>,
+}
+
+lazy_static! {
+    /// DEPLOYS is the deployment ids sampled by request inspector
+    static ref DEPLOYS: RwLock<HashSet<String>> = RwLock::new(HashSet::new());
+    /// RECORDS is the ring buffer of sampled requests
+    static ref RECORDS: Mutex<VecDeque<Record>> = Mutex::new(VecDeque::new());
+}
+
+/// set_deploys replaces the deployment ids sampled by request inspector if changed
+pub fn set_deploys(deploy_ids: HashSet<String>) {
+    let mut current = DEPLOYS.write().unwrap();
+    if *current == deploy_ids {
+        return;
+    }
+    let count = deploy_ids.len();
+    *current = deploy_ids;
+    info!("Reload inspecting deployments: {}", count);
+}
+
+/// is_enabled returns whether requests of the deployment are sampled
+pub fn is_enabled(deploy_id: &str) -> bool {
+    DEPLOYS.read().unwrap().contains(deploy_id)
+}
+
+/// push adds a record to ring buffer
+pub fn push(record: Record) {
+    let mut records = RECORDS.lock().unwrap();
+    if records.len() >= MAX_RECORDS {
+        records.pop_front();
+    }
+    records.push_back(record);
+}
+
+/// drain takes all records from ring buffer
+pub fn drain() -> Vec<Record> {
+    RECORDS.lock().unwrap().drain(..).collect()
+}
@@ -29,5 +29,8 @@ pub fn get_hostname() -> Result<String> {
     Ok(h)
 }
 
+pub mod crypto;
+pub mod inspect;
 pub mod logging;
+pub mod routes;
 pub mod version;
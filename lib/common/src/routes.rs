@@ -0,0 +1,97 @@
+use lazy_static::lazy_static;
+use rand::Rng;
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+use tracing::info;
+
+/// RouteLimits are the runtime limits in deployment spec, None means no limit.
+/// cpu time is in milliseconds, memory is in MB, wall time is in seconds.
+#[derive(Default, Clone, Debug, PartialEq)]
+pub struct RouteLimits {
+    pub cpu_time: Option<u64>,
+    pub memory: Option<u64>,
+    pub wall_time: Option<u64>,
+    pub fetch_count: Option<u64>,
+}
+
+/// Route is the deployment that a domain routes to
+#[derive(Default, Clone, Debug, PartialEq)]
+pub struct Route {
+    pub wasm_module: String,
+    pub user_id: String,
+    pub project_id: String,
+    pub deploy_id: String,
+    /// preview routes of dev domain always serve their own deployment
+    pub preview: bool,
+    pub limits: RouteLimits,
+}
+
+/// Canary is the deployment receiving weighted traffic of a project
+#[derive(Default, Clone, Debug, PartialEq)]
+pub struct Canary {
+    pub route: Route,
+    /// weight is the percent of project traffic, 0-100
+    pub weight: u32,
+}
+
+lazy_static! {
+    /// ROUTES is the domain to deployment table, replaced as a whole when confs change
+    static ref ROUTES: RwLock<Arc<HashMap<String, Route>>> = RwLock::new(Arc::new(HashMap::new()));
+    /// CANARIES is the project id to canary deployment table
+    static ref CANARIES: RwLock<Arc<HashMap<String, Canary>>> = RwLock::new(Arc::new(HashMap::new()));
+}
+
+/// reload replaces the routes table if changed, key is domain
+pub fn reload(routes: HashMap<String, Route>) {
+    let routes: HashMap<String, Route> = routes
+        .into_iter()
+        .map(|(domain, route)| (domain.to_lowercase(), route))
+        .collect();
+    let mut current = ROUTES.write().unwrap();
+    if **current == routes {
+        return;
+    }
+    let count = routes.len();
+    *current = Arc::new(routes);
+    info!("Reload routes: {}", count);
+}
+
+/// get gets route by host header, port is ignored
+pub fn get(host: &str) -> Option<Route> {
+    let domain = host.split(':').next().unwrap_or_default().to_lowercase();
+    let routes = ROUTES.read().unwrap().clone();
+    routes.get(&domain).cloned()
+}
+
+/// reload_canaries replaces the canaries table if changed, key is project id
+pub fn reload_canaries(canaries: HashMap<String, Canary>) {
+    let mut current = CANARIES.write().unwrap();
+    if **current == canaries {
+        return;
+    }
+    let count = canaries.len();
+    *current = Arc::new(canaries);
+    info!("Reload canaries: {}", count);
+}
+
+/// split picks canary route by weight for the project of stable route,
+/// preview routes are not split, returns the route to serve and whether it is canary
+pub fn split(route: Route) -> (Route, bool) {
+    let canaries = CANARIES.read().unwrap().clone();
+    let Some(canary) = canaries.get(&route.project_id) else {
+        return (route, false);
+    };
+    // requests to canary domain itself are not split
+    if canary.route.deploy_id == route.deploy_id {
+        return (route, true);
+    }
+    if route.preview {
+        return (route, false);
+    }
+    if rand::thread_rng().gen_range(0..100) < canary.weight {
+        return (canary.route.clone(), true);
+    }
+    (route, false)
+}
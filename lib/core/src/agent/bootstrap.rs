@@ -0,0 +1,96 @@
+use super::Item;
+use anyhow::{anyhow, Result};
+use std::sync::Arc;
+use tokio::{sync::Semaphore, task::JoinSet, time::Instant};
+use tracing::{info, instrument, warn, Instrument};
+
+/// WARM_CONCURRENCY is the max count of workers warming up at the same time
+const WARM_CONCURRENCY: usize = 4;
+
+/// check_wasm_file checks wasm file of item exists and its md5 matches file_hash
+fn check_wasm_file(item: &Item, dir: &str) -> Result<()> {
+    let wasm_file = format!("{}/{}", dir, item.file_name);
+    let content = std::fs::read(&wasm_file)
+        .map_err(|e| anyhow!("read wasm file error: {}, file: {}", e, wasm_file))?;
+    let content_md5 = format!("{:x}", md5::compute(&content));
+    if content_md5 != item.file_hash {
+        return Err(anyhow!(
+            "wasm hash dismatch: real: {}, expect: {}, file: {}",
+            content_md5,
+            item.file_hash,
+            wasm_file,
+        ));
+    }
+    Ok(())
+}
+
+/// init_from_confs loads last synced confs.json to serve deployments before server is reachable.
+/// It checks wasm files, rebuilds traefik files and routes, then warms workers in background,
+/// so worker server can start serving at once. Cold workers are loaded by requests.
+#[instrument("[AGT-BOOT]", skip_all)]
+pub async fn init_from_confs(dir: &str, service_name: &str) -> Result<()> {
+    let st = Instant::now();
+    let conf_file = format!("{}/confs.json", dir);
+    if !std::path::Path::new(&conf_file).exists() {
+        info!("No confs file: {}", conf_file);
+        return Ok(());
+    }
+    let content = std::fs::read_to_string(&conf_file)?;
+    let items: Vec<Item> = serde_json::from_str(&content)?;
+    let total = items.len();
+
+    let mut valid_items = vec![];
+    for item in items {
+        if let Err(e) = check_wasm_file(&item, dir) {
+            warn!(domain = item.domain, "Skip item: {}", e);
+            continue;
+        }
+        if let Err(e) = super::task::write_traefik(&item, dir, service_name) {
+            warn!(domain = item.domain, "Write traefik error: {}", e);
+            continue;
+        }
+        valid_items.push(item);
+    }
+    let count = valid_items.len();
+    let modules = valid_items
+        .iter()
+        .map(|item| (item.deploy_id.to_string(), item.file_name.clone()))
+        .collect();
+    super::sync::apply_items(valid_items);
+    info!(
+        elapsed = %st.elapsed().as_millis(),
+        "Load confs: {}/{}", count, total
+    );
+    tokio::spawn(warm_workers(modules).in_current_span());
+    Ok(())
+}
+
+/// warm_workers prepares workers of deployments concurrently with bounded concurrency,
+/// modules are pairs of deploy id and wasm file name
+async fn warm_workers(modules: Vec<(String, String)>) {
+    let st = Instant::now();
+    let total = modules.len();
+    let semaphore = Arc::new(Semaphore::new(WARM_CONCURRENCY));
+    let mut set = JoinSet::new();
+    for (deploy_id, module) in modules {
+        let permit = semaphore.clone().acquire_owned().await;
+        set.spawn(async move {
+            let _permit = permit;
+            if let Err(e) = land_wasm_host::pool::insert_worker(&deploy_id, &module, true).await {
+                warn!(deploy_id, "Prepare worker error: {}", e);
+                return false;
+            }
+            true
+        });
+    }
+    let mut count = 0;
+    while let Some(res) = set.join_next().await {
+        if matches!(res, Ok(true)) {
+            count += 1;
+        }
+    }
+    info!(
+        elapsed = %st.elapsed().as_millis(),
+        "Warm workers: {}/{}", count, total
+    );
+}
@@ -1,7 +1,14 @@
 use anyhow::Result;
-use land_common::obj_hash;
-use land_dao::{deploys, settings, store};
+use land_common::{
+    obj_hash,
+    routes::{Route, RouteLimits},
+};
+use land_dao::{
+    deploys::{self, DeployType, Spec},
+    envs, projects, settings, store,
+};
 use lazy_static::lazy_static;
+use std::collections::HashMap;
 use tokio::{sync::Mutex, time::Instant};
 use tracing::{debug, instrument, warn};
 
@@ -14,7 +21,48 @@ pub struct Item {
     pub file_name: String,
     pub download_url: String,
     pub file_hash: String,
+    /// aot_url is the url of precompiled aot artifact, empty if not compiled
+    #[serde(default)]
+    pub aot_url: String,
+    #[serde(default)]
+    pub aot_hash: String,
+    /// aot_engine is the fingerprint of engine that compiles aot artifact
+    #[serde(default)]
+    pub aot_engine: String,
     pub domain: String,
+    #[serde(default)]
+    pub spec: Spec,
+    #[serde(default)]
+    pub envs: HashMap<String, String>,
+    /// canary_weight is the percent of project traffic to this deployment, 0 is not canary
+    #[serde(default)]
+    pub canary_weight: u32,
+    /// inspect means worker samples requests of this deployment to request inspector
+    #[serde(default)]
+    pub inspect: bool,
+    /// preview means the domain is dev domain, its requests are not split to canary
+    #[serde(default)]
+    pub preview: bool,
+}
+
+impl Item {
+    /// route converts item to route of worker built-in router
+    pub fn route(&self) -> Route {
+        let limit = |v: Option<i32>| v.map(|v| v.max(0) as u64);
+        Route {
+            wasm_module: self.file_name.clone(),
+            user_id: self.user_id.to_string(),
+            project_id: self.project_id.to_string(),
+            deploy_id: self.deploy_id.to_string(),
+            preview: self.preview,
+            limits: RouteLimits {
+                cpu_time: limit(self.spec.cpu_time_limit),
+                memory: limit(self.spec.memory_limit),
+                wall_time: limit(self.spec.wall_time_limit),
+                fetch_count: limit(self.spec.fetch_limit),
+            },
+        }
+    }
 }
 
 /// init_confs is used to generate confs in background
@@ -41,11 +89,19 @@ lazy_static! {
 #[instrument("[AGENT-CONFS]")]
 pub async fn gen() -> anyhow::Result<()> {
     let st = Instant::now();
+    // empty ids should generate empty confs, to remove all deployments from workers
     let ids = deploys::success_ids().await?;
-    if ids.is_empty() {
-        return Ok(());
-    }
-    let ids_hash = obj_hash(ids.clone())?;
+    // envs changes should regenerate confs too
+    let envs_updated_at = envs::latest_updated_at().await?;
+    // canary weights changes should regenerate confs too
+    let canaries: Vec<(i32, i32)> = projects::list_canaries()
+        .await?
+        .iter()
+        .map(|p| (p.canary_deploy_id, p.canary_weight))
+        .collect();
+    // inspecting projects changes should regenerate confs too
+    let inspecting = projects::list_inspecting().await?;
+    let ids_hash = obj_hash((ids.clone(), envs_updated_at, canaries, inspecting))?;
     let mut confs = CONFS.lock().await;
     if confs.0 == ids_hash {
         // debug!("No changed");
@@ -58,12 +114,21 @@ pub async fn gen() -> anyhow::Result<()> {
     Ok(())
 }
 
-async fn gen_confs(ids: Vec<i32>) -> Result<Vec<Item>> {
+/// gen_confs generates items of deployments by ids
+pub(crate) async fn gen_confs(ids: Vec<i32>) -> Result<Vec<Item>> {
     let domain_settings = settings::get_domain_settings().await?;
 
     // get deploys data
     let deploy_data = deploys::list_by_ids(ids.clone()).await?;
     let storage_data = store::list_success_by_deploys(ids).await?;
+    let project_ids = deploy_data.iter().map(|dp| dp.project_id).collect();
+    let envs_data = envs::list_values(project_ids).await?;
+    let canaries: HashMap<i32, i32> = projects::list_canaries()
+        .await?
+        .into_iter()
+        .map(|p| (p.canary_deploy_id, p.canary_weight))
+        .collect();
+    let inspecting = projects::list_inspecting().await?;
 
     // build confs
     let mut items = Vec::new();
@@ -83,7 +148,21 @@ async fn gen_confs(ids: Vec<i32>) -> Result<Vec<Item>> {
             file_name: storage_item.path.clone(),
             download_url: storage_item.file_target.clone(),
             file_hash: storage_item.file_hash.clone(),
+            aot_url: storage_item.aot_target.clone(),
+            aot_hash: storage_item.aot_hash.clone(),
+            aot_engine: storage_item.aot_engine.clone(),
             domain,
+            spec: Spec::from_model(&deploy),
+            envs: envs_data
+                .get(&deploy.project_id)
+                .cloned()
+                .unwrap_or_default(),
+            canary_weight: canaries
+                .get(&deploy.id)
+                .map(|w| (*w).clamp(0, 100) as u32)
+                .unwrap_or_default(),
+            inspect: inspecting.contains(&deploy.project_id),
+            preview: deploy.deploy_type == DeployType::Development.to_string(),
         };
         items.push(item);
     }
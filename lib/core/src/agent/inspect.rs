@@ -0,0 +1,112 @@
+use anyhow::{anyhow, Result};
+use land_common::inspect::{self, Record};
+use lazy_static::lazy_static;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+};
+use tracing::{debug, instrument, warn};
+
+/// MAX_PROJECT_RECORDS is max count of sampled requests kept for each project
+const MAX_PROJECT_RECORDS: usize = 100;
+
+/// init_inspect starts background task to ship sampled requests to server
+pub async fn init_inspect(addr: String, token: String) {
+    debug!("agent init_inspect");
+
+    // init client
+    super::CLIENT_ONCE.call_once(|| {
+        let client = Client::new();
+        super::CLIENT.set(client).unwrap();
+    });
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(std::time::Duration::from_secs(1));
+        ticker.tick().await;
+        loop {
+            match ship(addr.clone(), token.clone()).await {
+                Ok(_) => {}
+                Err(e) => {
+                    warn!("agent ship inspect error: {:?}", e);
+                }
+            };
+            ticker.tick().await;
+        }
+    });
+}
+
+#[instrument("[AGT-INSPECT]", skip_all)]
+async fn ship(addr: String, token: String) -> Result<()> {
+    let records = inspect::drain();
+    if records.is_empty() {
+        return Ok(());
+    }
+    let ipinfo = super::get_ip().await;
+    let client = super::CLIENT.get().unwrap();
+    let api = format!("{}/worker-api/inspect?ip={}", addr, ipinfo.ip);
+    let token = format!("Bearer {}", token);
+    let res = client
+        .post(api)
+        .header("Authorization", token)
+        .json(&records)
+        .send()
+        .await?;
+    let status_code = res.status().as_u16();
+    if status_code >= 400 {
+        let content = res.text().await?;
+        return Err(anyhow!("Bad status:{}, Error:{}", status_code, content));
+    }
+    debug!("ship inspect records: {}", records.len());
+    Ok(())
+}
+
+/// InspectItem is a sampled request with sequence number for tailing
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct InspectItem {
+    pub seq: u64,
+    #[serde(flatten)]
+    pub record: Record,
+}
+
+lazy_static! {
+    /// INSPECTS is sampled requests of each project, and the last sequence number
+    static ref INSPECTS: Mutex<(u64, HashMap<i32, VecDeque<InspectItem>>)> =
+        Mutex::new((0, HashMap::new()));
+}
+
+/// save_inspect saves sampled requests shipped by workers, only recent requests are kept
+pub fn save_inspect(records: Vec<Record>) {
+    let mut inspects = INSPECTS.lock().unwrap();
+    for record in records {
+        inspects.0 += 1;
+        let seq = inspects.0;
+        let items = inspects.1.entry(record.project_id).or_default();
+        if items.len() >= MAX_PROJECT_RECORDS {
+            items.pop_front();
+        }
+        items.push_back(InspectItem { seq, record });
+    }
+}
+
+/// list_inspect lists sampled requests of a project after the sequence number
+pub fn list_inspect(project_id: i32, after_seq: u64) -> Vec<InspectItem> {
+    let inspects = INSPECTS.lock().unwrap();
+    inspects
+        .1
+        .get(&project_id)
+        .map(|items| {
+            items
+                .iter()
+                .filter(|item| item.seq > after_seq)
+                .cloned()
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// clear_inspect removes sampled requests of a project
+pub fn clear_inspect(project_id: i32) {
+    INSPECTS.lock().unwrap().1.remove(&project_id);
+}
@@ -0,0 +1,102 @@
+use anyhow::{anyhow, Result};
+use land_dao::project_logs;
+use land_wasm_host::logs;
+use once_cell::sync::OnceCell;
+use reqwest::Client;
+use tracing::{debug, info, instrument, warn};
+
+/// MAX_BATCH_SIZE is max bytes of log messages shipped in one request
+const MAX_BATCH_SIZE: usize = 1024 * 1024;
+
+/// MAX_BATCH_COUNT is max count of log lines shipped in one request
+const MAX_BATCH_COUNT: usize = 1000;
+
+/// init_logs starts background task to ship guest logs to server
+pub async fn init_logs(addr: String, token: String) {
+    debug!("agent init_logs");
+
+    // init client
+    super::CLIENT_ONCE.call_once(|| {
+        let client = Client::new();
+        super::CLIENT.set(client).unwrap();
+    });
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(std::time::Duration::from_secs(3));
+        ticker.tick().await;
+        loop {
+            match ship(addr.clone(), token.clone()).await {
+                Ok(_) => {}
+                Err(e) => {
+                    warn!("agent ship logs error: {:?}", e);
+                }
+            };
+            ticker.tick().await;
+        }
+    });
+}
+
+#[instrument("[AGT-LOGS]", skip_all)]
+async fn ship(addr: String, token: String) -> Result<()> {
+    let ipinfo = super::get_ip().await;
+    let client = super::CLIENT.get().unwrap();
+    let api = format!("{}/worker-api/logs?ip={}", addr, ipinfo.ip);
+    let token = format!("Bearer {}", token);
+    loop {
+        let entries = logs::drain(MAX_BATCH_SIZE, MAX_BATCH_COUNT);
+        if entries.is_empty() {
+            return Ok(());
+        }
+        // failed batch is dropped, logs are best effort and never block requests
+        let res = client
+            .post(&api)
+            .header("Authorization", &token)
+            .json(&entries)
+            .send()
+            .await?;
+        let status_code = res.status().as_u16();
+        if status_code >= 400 {
+            let content = res.text().await?;
+            return Err(anyhow!(
+                "Bad status:{}, Error:{}, dropped: {}",
+                status_code,
+                content,
+                entries.len()
+            ));
+        }
+        debug!("ship logs: {}", entries.len());
+    }
+}
+
+/// RETENTION_HOURS is hours to keep guest logs
+static RETENTION_HOURS: OnceCell<i64> = OnceCell::new();
+
+/// logs_retention returns hours to keep guest logs
+pub fn logs_retention() -> i64 {
+    *RETENTION_HOURS.get().unwrap_or(&0)
+}
+
+/// init_logs_retention starts background task to delete guest logs older than retention hours
+pub async fn init_logs_retention(hours: i64) {
+    debug!("agent init_logs_retention: {}h", hours);
+    RETENTION_HOURS.set(hours).unwrap();
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(std::time::Duration::from_secs(600));
+        ticker.tick().await;
+        loop {
+            let t = chrono::Utc::now().naive_utc() - chrono::Duration::hours(hours);
+            match project_logs::delete_before(t).await {
+                Ok(count) => {
+                    if count > 0 {
+                        info!("Delete expired logs: {}", count);
+                    }
+                }
+                Err(e) => {
+                    warn!("agent logs retention error: {:?}", e);
+                }
+            };
+            ticker.tick().await;
+        }
+    });
+}
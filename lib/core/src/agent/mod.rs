@@ -5,14 +5,22 @@ use serde::{Deserialize, Serialize};
 use std::sync::Once;
 use tracing::info;
 
+mod bootstrap;
 mod confs;
+mod inspect;
 mod livings;
+mod logs;
 mod sync;
 mod task;
 mod traefik;
 
+pub use bootstrap::init_from_confs;
+pub(crate) use confs::gen_confs;
 pub use confs::{get_confs, init_confs, Item};
+pub use inspect::{clear_inspect, init_inspect, list_inspect, save_inspect, InspectItem};
+pub use land_common::inspect::Record as InspectRecord;
 pub use livings::{init_livings, set_living};
+pub use logs::{init_logs, init_logs_retention, logs_retention};
 pub use sync::init_sync;
 pub use task::init_task;
 
@@ -57,5 +65,49 @@ pub async fn get_ip() -> IP {
     ip_data.clone()
 }
 
+/// write_private writes content to file that only owner can read and write,
+/// used for files with secrets in data dir
+fn write_private(path: &str, content: &[u8]) -> Result<()> {
+    use std::io::Write;
+    #[cfg(unix)]
+    use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+
+    let mut opts = std::fs::OpenOptions::new();
+    opts.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    opts.mode(0o600);
+    let mut file = opts.open(path)?;
+    // mode only applies to new file, fix permissions of file created before
+    #[cfg(unix)]
+    file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+    file.write_all(content)?;
+    Ok(())
+}
+
+/// SECRET is the per-worker secret to sign routing headers in traefik confs
+static SECRET: OnceCell<String> = OnceCell::new();
+
+/// init_secret loads worker secret from data dir, creates a random one if not exist
+pub fn init_secret(dir: &str) -> Result<String> {
+    std::fs::create_dir_all(dir)?;
+    let secret_file = format!("{}/.secret", dir);
+    let secret = match std::fs::read_to_string(&secret_file) {
+        Ok(s) if !s.trim().is_empty() => s.trim().to_string(),
+        _ => {
+            let s = land_common::rand_string(64);
+            write_private(&secret_file, s.as_bytes())?;
+            info!("Create worker secret: {}", secret_file);
+            s
+        }
+    };
+    SECRET.set(secret.clone()).unwrap();
+    Ok(secret)
+}
+
+/// get_secret gets worker secret
+fn get_secret() -> &'static str {
+    SECRET.get().unwrap()
+}
+
 static CLIENT: OnceCell<Client> = OnceCell::new();
 static CLIENT_ONCE: Once = Once::new();
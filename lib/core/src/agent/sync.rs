@@ -1,7 +1,10 @@
 use super::Item;
 use anyhow::{anyhow, Result};
+use land_common::routes::{self, Canary};
+use land_wasm_host::egress::Policy;
 use reqwest::Client;
 use serde::Deserialize;
+use std::collections::HashMap;
 use tracing::{debug, instrument, warn};
 
 #[derive(Deserialize, Default, Clone, Debug)]
@@ -38,12 +41,15 @@ async fn request(addr: String, token: String, dir: String) -> Result<()> {
     }
     let resp: SyncResponse = res.json().await?;
     let conf_file = format!("{}/confs.json", dir);
-    if resp.status != "ok"{
+    if resp.status != "ok" {
         return Err(anyhow!("sync error: {}", resp.message));
     }
     // debug!("sync data: {}, {}", resp.status, resp.message);
-    // write resp to file
-    std::fs::write(conf_file, serde_json::to_string(&resp.data).unwrap()).unwrap();
+    // write resp to file, it contains decrypted secrets of projects
+    let content = serde_json::to_vec(&resp.data)?;
+    super::write_private(&conf_file, &content)?;
+
+    apply_items(resp.data);
     Ok(())
 }
 
@@ -71,3 +77,62 @@ pub async fn init_sync(addr: String, token: String, dir: String) {
         }
     });
 }
+
+/// apply_items updates routes, egress policies and envs of worker by synced items
+pub(super) fn apply_items(items: Vec<Item>) {
+    // reload routes table for self-routing
+    let routes = items
+        .iter()
+        .map(|item| (item.domain.clone(), item.route()))
+        .collect();
+    routes::reload(routes);
+
+    // reload canary deployments of projects
+    let canaries = items
+        .iter()
+        .filter(|item| item.canary_weight > 0)
+        .map(|item| {
+            let canary = Canary {
+                route: item.route(),
+                weight: item.canary_weight,
+            };
+            (item.project_id.to_string(), canary)
+        })
+        .collect();
+    routes::reload_canaries(canaries);
+
+    // update deployments sampled by request inspector
+    let inspecting = items
+        .iter()
+        .filter(|item| item.inspect)
+        .map(|item| item.deploy_id.to_string())
+        .collect();
+    land_common::inspect::set_deploys(inspecting);
+
+    // update egress policies of deployments, deployments of a project may have different specs
+    let mut policies = HashMap::new();
+    for item in items.iter() {
+        match Policy::new(&item.spec.egress_allow, &item.spec.egress_deny, false) {
+            Ok(policy) => {
+                policies.insert(item.deploy_id.to_string(), policy);
+            }
+            Err(e) => {
+                warn!(deploy_id = item.deploy_id, "Invalid egress policy: {}", e);
+            }
+        }
+    }
+    land_wasm_host::egress::set_deploy_policies(policies);
+    // release http clients of removed deployments
+    let deploy_ids = items
+        .iter()
+        .map(|item| item.deploy_id.to_string())
+        .collect();
+    land_wasm_host::hostcall::retain_clients(&deploy_ids);
+
+    // update envs of projects
+    let envs = items
+        .into_iter()
+        .map(|item| (item.project_id.to_string(), item.envs))
+        .collect();
+    land_wasm_host::envs::set_all(envs);
+}
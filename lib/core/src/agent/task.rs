@@ -118,14 +118,46 @@ async fn request(addr: String, token: String, dir: String, service_name: String)
 }
 
 async fn handle_each_task(t: Task, dir: String, service_name: String) -> Result<()> {
-    if t.task_type == TaskType::DeployWasmToWorker.to_string() {
-        let item: Item = serde_json::from_str(&t.content)?;
+    let item: Item = serde_json::from_str(&t.content)?;
+    if t.task_type == TaskType::DeployWasmToWorker.to_string()
+        || t.task_type == TaskType::EnableDeploymentOnWorker.to_string()
+    {
         handle_each_agent_item(item, dir.clone(), service_name.clone()).await?;
         return Ok(());
     }
+    if t.task_type == TaskType::RemoveDeploymentFromWorker.to_string() {
+        remove_agent_item(item, &dir, true)?;
+        return Ok(());
+    }
+    if t.task_type == TaskType::DisableDeploymentOnWorker.to_string() {
+        remove_agent_item(item, &dir, false)?;
+        return Ok(());
+    }
     Err(anyhow!("unknown task type: {}", t.task_type))
 }
 
+/// remove_agent_item removes traefik file and cached worker of item,
+/// and removes wasm files if remove_files is true
+fn remove_agent_item(item: Item, dir: &str, remove_files: bool) -> Result<()> {
+    // 1. remove traefik file, only if it is not replaced by other deployment of same domain
+    let traefik_file = format!("{}/traefik/{}.yaml", dir, item.domain.replace('.', "_"));
+    if let Ok(content) = std::fs::read_to_string(&traefik_file) {
+        if content.contains(&format!("r-{}", item.task_id)) {
+            std::fs::remove_file(&traefik_file)?;
+            debug!("remove traefik success: {}", traefik_file);
+        }
+    }
+
+    // 2. evict worker and remove files
+    land_wasm_host::pool::remove_worker(
+        &item.deploy_id.to_string(),
+        &item.file_name,
+        remove_files,
+    )?;
+    debug!("remove worker success: {}", item.file_name);
+    Ok(())
+}
+
 async fn handle_each_agent_item(item: Item, dir: String, service_name: String) -> Result<()> {
     let wasm_target_file = format!("{}/{}", dir, item.file_name);
 
@@ -155,18 +187,71 @@ async fn handle_each_agent_item(item: Item, dir: String, service_name: String) -
         debug!("download success: {}", wasm_target_file);
     }
 
-    // 2. generate traefic file
+    // 2. download precompiled aot file, fallback to compile locally if failed
+    if let Err(e) = download_aot(&item, &wasm_target_file).await {
+        warn!(file_name = item.file_name, "download aot error: {:?}", e);
+    }
+
+    // 3. warm up worker before routing requests to it
+    land_wasm_host::pool::insert_worker(&item.deploy_id.to_string(), &item.file_name, true).await?;
+    debug!("prepare worker success: {}", item.file_name);
+
+    // 4. generate traefic file
+    write_traefik(&item, &dir, &service_name)?;
+
+    Ok(())
+}
+
+/// download_aot downloads precompiled aot file of item,
+/// it skips if aot file exists or it is compiled by different engine
+async fn download_aot(item: &Item, wasm_target_file: &str) -> Result<()> {
+    if item.aot_url.is_empty() {
+        return Ok(());
+    }
+    let aot_target_file = land_wasm_host::pool::aot_file_path(wasm_target_file)?;
+    if Path::new(&aot_target_file).exists() {
+        return Ok(());
+    }
+    let engine = land_wasm_host::fingerprint()?;
+    if item.aot_engine != engine {
+        debug!(
+            "skip aot, engine dismatch: local: {}, expect: {}",
+            engine, item.aot_engine
+        );
+        return Ok(());
+    }
+    let resp = reqwest::get(&item.aot_url).await?;
+    if resp.status().as_u16() != 200 {
+        return Err(anyhow!(
+            "download aot error: {}, url: {}",
+            resp.status(),
+            item.aot_url
+        ));
+    }
+    let content = resp.bytes().await?;
+    let content_md5 = format!("{:x}", md5::compute(&content));
+    if content_md5 != item.aot_hash {
+        return Err(anyhow!(
+            "download aot hash dismatch: real: {}, expect: {}, url: {}",
+            content_md5,
+            item.aot_hash,
+            item.aot_url,
+        ));
+    }
+    std::fs::write(&aot_target_file, &content)?;
+    land_wasm_host::aot::write_manifest(wasm_target_file, &aot_target_file, &content)?;
+    debug!("download aot success: {}", aot_target_file);
+    Ok(())
+}
+
+/// write_traefik generates traefik file of item
+pub(super) fn write_traefik(item: &Item, dir: &str, service_name: &str) -> Result<()> {
     let traefik_file = format!("{}/traefik/{}.yaml", dir, item.domain.replace('.', "_"));
     let traefik_dir = format!("{}/traefik", dir);
     std::fs::create_dir_all(traefik_dir)?;
-    let confs = super::traefik::build(&item, &service_name)?;
+    let confs = super::traefik::build(item, service_name, super::get_secret())?;
     let content = serde_yaml::to_string(&confs)?;
     std::fs::write(&traefik_file, content)?;
     debug!("generate traefik success: {}", traefik_file);
-
-    // 3. prepare worker
-    land_wasm_host::pool::prepare_worker(&item.file_name, true).await?;
-    debug!("prepare worker success: {}", item.file_name);
-
     Ok(())
 }
@@ -1,5 +1,6 @@
 use super::Item;
 use anyhow::Result;
+use land_common::crypto;
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 
@@ -9,7 +10,7 @@ pub struct TraefikConfs {
 }
 
 /// build builds the TraefikConfs for the given TaskValue.
-pub fn build(item: &Item, service_name: &str) -> Result<TraefikConfs> {
+pub fn build(item: &Item, service_name: &str, secret: &str) -> Result<TraefikConfs> {
     let mut traefik_confs = HttpTraefikConfs {
         //services: HashMap::new(),
         routers: BTreeMap::new(),
@@ -31,6 +32,30 @@ pub fn build(item: &Item, service_name: &str) -> Result<TraefikConfs> {
     headers
         .custom_request_headers
         .insert("x-land-did".to_string(), item.deploy_id.to_string());
+    if item.preview {
+        headers
+            .custom_request_headers
+            .insert("x-land-preview".to_string(), "1".to_string());
+    }
+    // set runtime limits of deployment spec
+    let limits = [
+        ("x-land-cpu", item.spec.cpu_time_limit),
+        ("x-land-mem", item.spec.memory_limit),
+        ("x-land-wall", item.spec.wall_time_limit),
+        ("x-land-fetch", item.spec.fetch_limit),
+    ];
+    for (key, value) in limits {
+        if let Some(value) = value {
+            headers
+                .custom_request_headers
+                .insert(key.to_string(), value.to_string());
+        }
+    }
+    // sign routing headers, worker rejects requests without valid signature
+    let signature = crypto::sign_headers(secret, &headers.custom_request_headers);
+    headers
+        .custom_request_headers
+        .insert(crypto::SIGNATURE_HEADER.to_string(), signature);
     traefik_confs
         .middlewares
         .insert(format!("m-{}", item.task_id), MiddlewareGroup { headers });
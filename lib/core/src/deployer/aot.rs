@@ -0,0 +1,46 @@
+use super::buildlog;
+use anyhow::Result;
+use land_dao::{
+    models::{deployment, storage},
+    store,
+};
+use land_wasm_host::Worker;
+use std::time::Instant;
+use tracing::warn;
+
+/// precompile compiles aot artifact of wasm component and saves it next to the wasm file,
+/// workers download it to be warm immediately. If it fails, workers compile locally.
+pub(crate) async fn precompile(dp: &deployment::Model, record: &storage::Model, data: &[u8]) {
+    if let Err(e) = precompile_internal(dp, record, data).await {
+        warn!(dp_id = dp.id, "Precompile AOT failed: {:?}", e);
+        buildlog::append(dp.id, &format!("Precompile AOT failed: {}", e));
+    }
+}
+
+async fn precompile_internal(
+    dp: &deployment::Model,
+    record: &storage::Model,
+    data: &[u8],
+) -> Result<()> {
+    let st = Instant::now();
+    let bytes = data.to_vec();
+    // compile in blocking thread, it takes long time for large component
+    let aot_data = tokio::task::spawn_blocking(move || Worker::precompile(&bytes)).await??;
+    let engine = land_wasm_host::fingerprint()?;
+    let aot_name = land_wasm_host::pool::aot_file_path(&record.path)?;
+    let aot_hash = format!("{:x}", md5::compute(&aot_data));
+    let aot_size = aot_data.len();
+    crate::storage::save(&aot_name, aot_data).await?;
+    let aot_url = crate::storage::build_url(&aot_name).await?;
+    store::set_aot(record.id, &aot_url, &aot_hash, &engine).await?;
+    buildlog::append(
+        dp.id,
+        &format!(
+            "Precompile AOT done in {}ms, size: {} bytes, engine: {}",
+            st.elapsed().as_millis(),
+            aot_size,
+            engine
+        ),
+    );
+    Ok(())
+}
@@ -0,0 +1,89 @@
+use anyhow::Result;
+use land_dao::{deploy_logs, deploys, models::deployment};
+use land_wasm_gen::BuildLogger;
+use lazy_static::lazy_static;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+use tracing::warn;
+
+/// MAX_LOG_SIZE is max bytes of build log of a deployment
+const MAX_LOG_SIZE: usize = 512 * 1024;
+
+/// Log is build log of a deployment in progress
+#[derive(Default)]
+struct Log {
+    content: String,
+    truncated: bool,
+}
+
+lazy_static! {
+    /// LOGS is build logs of deployments in progress, saved to database when finished
+    static ref LOGS: Mutex<HashMap<i32, Log>> = Mutex::new(HashMap::new());
+}
+
+/// append appends message to build log of a deployment, each line with time prefix
+pub fn append(dp_id: i32, msg: &str) {
+    let now = chrono::Utc::now().format("%H:%M:%S%.3f");
+    let mut logs = LOGS.lock().unwrap();
+    let log = logs.entry(dp_id).or_default();
+    for line in msg.lines() {
+        if log.truncated {
+            break;
+        }
+        if log.content.len() > MAX_LOG_SIZE {
+            log.content.push_str("...(log truncated)\n");
+            log.truncated = true;
+            break;
+        }
+        log.content.push_str(&format!("[{}] {}\n", now, line));
+    }
+}
+
+/// logger returns build logger writing to build log of a deployment
+pub fn logger(dp_id: i32) -> BuildLogger {
+    Arc::new(move |msg: &str| append(dp_id, msg))
+}
+
+/// finish saves build log of a deployment to database
+pub(crate) async fn finish(dp: &deployment::Model) {
+    let content = LOGS
+        .lock()
+        .unwrap()
+        .get(&dp.id)
+        .map(|log| log.content.clone());
+    if let Some(content) = content {
+        if let Err(e) = deploy_logs::save(dp, &content).await {
+            warn!(dp_id = dp.id, "Save build log failed: {:?}", e);
+        }
+    }
+    // remove after saved, so readers always find the log in memory or database
+    LOGS.lock().unwrap().remove(&dp.id);
+}
+
+/// get_log returns build log of a deployment, and whether the deployment is in progress.
+/// Deployment is in progress until its deploy status is success or failed,
+/// its build log may not be started yet when it is waiting.
+pub async fn get_log(dp_id: i32) -> Result<(String, bool)> {
+    let running = match deploys::get_by_id(dp_id).await? {
+        Some(dp) => {
+            dp.deploy_status != deploys::Status::Success.to_string()
+                && dp.deploy_status != deploys::Status::Failed.to_string()
+        }
+        None => false,
+    };
+    let content = LOGS
+        .lock()
+        .unwrap()
+        .get(&dp_id)
+        .map(|log| log.content.clone());
+    if let Some(content) = content {
+        return Ok((content, running));
+    }
+    let content = deploy_logs::get_by_deploy(dp_id)
+        .await?
+        .map(|m| m.content)
+        .unwrap_or_default();
+    Ok((content, running))
+}
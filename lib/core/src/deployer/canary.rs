@@ -0,0 +1,29 @@
+use anyhow::{anyhow, Result};
+use land_dao::{
+    deploys::{self, DeployType, DeploymentStatus},
+    projects,
+};
+use tracing::info;
+
+/// set_canary splits weight percent of project traffic to current preview deployment,
+/// zero weight stops canary
+pub async fn set_canary(project_id: i32, weight: i32) -> Result<()> {
+    if !(0..=100).contains(&weight) {
+        return Err(anyhow!("Canary weight should be between 0 and 100"));
+    }
+    if weight == 0 {
+        projects::set_canary(project_id, 0, 0).await?;
+        info!(project_id, "Stop canary");
+        return Ok(());
+    }
+    let deploy_data =
+        deploys::list_success_by_project(project_id, DeploymentStatus::Active).await?;
+    let preview = deploy_data
+        .iter()
+        .filter(|dp| dp.deploy_type == DeployType::Development.to_string())
+        .max_by_key(|dp| dp.id)
+        .ok_or_else(|| anyhow!("No active preview deployment for canary"))?;
+    projects::set_canary(project_id, preview.id, weight).await?;
+    info!(project_id, dp_id = preview.id, weight, "Set canary");
+    Ok(())
+}
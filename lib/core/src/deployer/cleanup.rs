@@ -0,0 +1,105 @@
+use crate::agent::gen_confs;
+use anyhow::{anyhow, Result};
+use land_dao::{
+    deploy_task::{self, TaskType},
+    deploys::{self, DeploymentStatus},
+    models::deployment,
+    projects, store, workers,
+};
+use tracing::{debug, info, warn};
+
+/// create_worker_tasks creates task of deployment for each online worker
+async fn create_worker_tasks(dp: &deployment::Model, task_type: TaskType) -> Result<()> {
+    let mut items = gen_confs(vec![dp.id]).await?;
+    if items.is_empty() {
+        return Err(anyhow!("Storage not found for deploy {}", dp.id));
+    }
+    let mut item = items.remove(0);
+    // envs are synced by confs, not saved in task content
+    item.envs = Default::default();
+    let item_content = serde_json::to_string(&item)?;
+
+    let workers_value = workers::find_all(Some(workers::Status::Online)).await?;
+    for worker in workers_value.iter() {
+        let task = deploy_task::create(dp, task_type.clone(), &item_content, worker.id, &worker.ip)
+            .await?;
+        debug!("Create task: {:?}", task);
+    }
+    Ok(())
+}
+
+/// outdate_previous sets previous deployments of the same domain as outdated,
+/// and removes them from workers
+pub async fn outdate_previous(dp: &deployment::Model) -> Result<()> {
+    let deploy_data =
+        deploys::list_success_by_project(dp.project_id, DeploymentStatus::Active).await?;
+    let ids = deploy_data
+        .iter()
+        .map(|old| old.id)
+        .chain([dp.id])
+        .collect();
+    let storage_data = store::list_success_by_deploys(ids).await?;
+    let current_file = storage_data.get(&dp.id).map(|s| s.path.clone());
+    for old in deploy_data.iter() {
+        if old.id >= dp.id || old.domain != dp.domain {
+            continue;
+        }
+        deploys::set_status(old.id, DeploymentStatus::Outdated).await?;
+        // rollback deployment shares wasm file with old one, do not remove the file
+        let old_file = storage_data.get(&old.id).map(|s| s.path.clone());
+        let task_type = if old_file.is_some() && old_file == current_file {
+            TaskType::DisableDeploymentOnWorker
+        } else {
+            TaskType::RemoveDeploymentFromWorker
+        };
+        if let Err(e) = create_worker_tasks(old, task_type).await {
+            warn!(dp_id = old.id, "Create remove tasks failed: {:?}", e);
+            continue;
+        }
+        info!(dp_id = old.id, current_dp_id = dp.id, "Set outdated");
+    }
+    // stop canary if its deployment is outdated
+    if let Some(project) = projects::get_by_id(dp.project_id).await? {
+        let outdated = deploy_data.iter().any(|old| {
+            old.id == project.canary_deploy_id && old.id < dp.id && old.domain == dp.domain
+        });
+        if outdated {
+            projects::set_canary(project.id, 0, 0).await?;
+            info!(dp_id = project.canary_deploy_id, "Stop outdated canary");
+        }
+    }
+    Ok(())
+}
+
+/// disable_project disables a project and its deployments on workers
+pub async fn disable_project(project_id: i32) -> Result<()> {
+    // workers drop routes of disabled deployments from confs, tasks only apply it sooner
+    let deploy_data = projects::set_status_with_deploys(
+        project_id,
+        projects::Status::Disabled,
+        DeploymentStatus::Active,
+        DeploymentStatus::Disabled,
+    )
+    .await?;
+    for dp in deploy_data.iter() {
+        create_worker_tasks(dp, TaskType::DisableDeploymentOnWorker).await?;
+        info!(dp_id = dp.id, project_id, "Disable deployment");
+    }
+    Ok(())
+}
+
+/// enable_project enables a project and its disabled deployments on workers
+pub async fn enable_project(project_id: i32) -> Result<()> {
+    let deploy_data = projects::set_status_with_deploys(
+        project_id,
+        projects::Status::Active,
+        DeploymentStatus::Disabled,
+        DeploymentStatus::Active,
+    )
+    .await?;
+    for dp in deploy_data.iter() {
+        create_worker_tasks(dp, TaskType::EnableDeploymentOnWorker).await?;
+        info!(dp_id = dp.id, project_id, "Enable deployment");
+    }
+    Ok(())
+}
@@ -0,0 +1,89 @@
+use anyhow::Result;
+use land_dao::{
+    deploy_task::{self, TaskType},
+    deploys::{self, Status},
+    models::deploy_task as deploy_task_model,
+    settings, workers,
+};
+use std::collections::HashMap;
+use tracing::{debug, info, instrument, warn};
+
+/// init_dispatch starts dispatching missed deploy tasks to workers
+pub async fn init_dispatch() {
+    debug!("deployer init_dispatch");
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(std::time::Duration::from_secs(10));
+        ticker.tick().await;
+        loop {
+            match dispatch().await {
+                Ok(_) => {}
+                Err(e) => {
+                    warn!("deployer dispatch handle error: {:?}", e);
+                }
+            };
+            ticker.tick().await;
+        }
+    });
+}
+
+/// dispatch creates deploy tasks for workers that come online after deploying,
+/// and retries failed tasks of active deployments with backoff
+#[instrument("[DEPLOY-DISPATCH]")]
+async fn dispatch() -> Result<()> {
+    let workers_value = workers::find_all(Some(workers::Status::Online)).await?;
+    if workers_value.is_empty() {
+        return Ok(());
+    }
+    let mut deploy_data = deploys::list_by_ids(deploys::success_ids().await?).await?;
+    deploy_data.extend(deploys::list_by_deploy_status(Status::Deploying).await?);
+    if deploy_data.is_empty() {
+        return Ok(());
+    }
+    let task_ids = deploy_data.iter().map(|dp| dp.task_id.clone()).collect();
+    let tasks = deploy_task::list_by_task_ids(task_ids).await?;
+    let deploy_type = TaskType::DeployWasmToWorker.to_string();
+    let mut tasks_map: HashMap<String, Vec<deploy_task_model::Model>> = HashMap::new();
+    for task in tasks.into_iter().filter(|t| t.task_type == deploy_type) {
+        tasks_map
+            .entry(task.task_id.clone())
+            .or_default()
+            .push(task);
+    }
+
+    let deploy_settings = settings::get_deploy_settings().await?;
+    let now = chrono::Utc::now().naive_utc();
+    for dp in deploy_data.iter() {
+        let Some(dp_tasks) = tasks_map.get(&dp.task_id) else {
+            continue;
+        };
+        // deploying tasks are retried in review
+        let is_deploying = dp.deploy_status == Status::Deploying.to_string();
+        for worker in workers_value.iter() {
+            let worker_task = dp_tasks.iter().rev().find(|t| t.worker_ip == worker.ip);
+            let Some(worker_task) = worker_task else {
+                // task content is same for all workers
+                let content = &dp_tasks[0].task_content;
+                deploy_task::create(
+                    dp,
+                    TaskType::DeployWasmToWorker,
+                    content,
+                    worker.id,
+                    &worker.ip,
+                )
+                .await?;
+                info!(dp_id = dp.id, ip = worker.ip, "Dispatch missed task");
+                continue;
+            };
+            if is_deploying || worker_task.status != deploy_task::Status::Failed.to_string() {
+                continue;
+            }
+            // tasks out of retries are left failed, same as review
+            let elapsed = (now - worker_task.updated_at).num_seconds();
+            if deploy_settings.should_retry(worker_task.retry_count, elapsed) {
+                deploy_task::retry(worker_task.id, worker_task.retry_count + 1).await?;
+                info!(dp_id = dp.id, ip = worker.ip, "Retry failed task");
+            }
+        }
+    }
+    Ok(())
+}
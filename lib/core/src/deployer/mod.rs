@@ -1,5 +1,21 @@
-mod waiting;
+mod aot;
+mod buildlog;
+mod canary;
+mod cleanup;
+mod dispatch;
 mod review;
+mod rollback;
+mod upload;
+mod validate;
+mod waiting;
+mod watchdog;
 
-pub use waiting::init_waiting;
+pub use buildlog::get_log;
+pub use canary::set_canary;
+pub use cleanup::{disable_project, enable_project};
+pub use dispatch::init_dispatch;
 pub use review::init_review;
+pub use rollback::{promote, rollback};
+pub use upload::{upload, MAX_UPLOAD_SIZE};
+pub use waiting::init_waiting;
+pub use watchdog::init_watchdog;
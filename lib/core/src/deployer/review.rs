@@ -1,10 +1,16 @@
-use crate::deployer::waiting::{set_failed, set_success};
+use crate::deployer::{
+    buildlog,
+    waiting::{set_failed, set_success},
+};
 use anyhow::{anyhow, Result};
 use land_dao::{
     deploy_task,
     deploys::{self, Status},
     models::deployment,
+    settings::{self, DeploySettings},
+    workers,
 };
+use std::collections::HashSet;
 use tracing::{debug, info, instrument, warn};
 
 /// init_review starts handling waiting deploy tasks
@@ -32,11 +38,17 @@ async fn review() -> Result<()> {
         return Ok(());
     }
     info!("Review: {}", deploy_data.len());
+    let deploy_settings = settings::get_deploy_settings().await?;
+    let online_ips: HashSet<String> = workers::find_all(Some(workers::Status::Online))
+        .await?
+        .into_iter()
+        .map(|w| w.ip)
+        .collect();
     for dp in deploy_data.iter() {
-        match handle_one(dp).await {
+        match handle_one(dp, &deploy_settings, &online_ips).await {
             Ok(_) => {}
             Err(e) => {
-                set_failed(dp.id, dp.project_id, e.to_string().as_str()).await?;
+                set_failed(dp, e.to_string().as_str()).await?;
                 warn!(dp_id = dp.id, "deployer waiting handle error: {:?}", e);
             }
         }
@@ -44,24 +56,35 @@ async fn review() -> Result<()> {
     Ok(())
 }
 
-async fn handle_one(dp: &deployment::Model) -> Result<()> {
+async fn handle_one(
+    dp: &deployment::Model,
+    deploy_settings: &DeploySettings,
+    online_ips: &HashSet<String>,
+) -> Result<()> {
     debug!("Handle review: {}", dp.id);
+    // tasks count may change when workers come online during deploying
     let tasks = deploy_task::list(None, None, Some(dp.task_id.clone())).await?;
-    if tasks.len() as i32 != dp.total_count {
-        return Err(anyhow!("Task count not match"));
+    if tasks.is_empty() {
+        return Err(anyhow!("No deploy task"));
     }
+    let now = chrono::Utc::now().naive_utc();
     let mut success_count = 0;
+    let mut failed_count = 0;
     let mut failed_message = "".to_string();
-    let mut done_count = 0;
+    let mut doing_count = 0;
     for task in tasks.iter() {
+        let is_online = online_ips.contains(&task.worker_ip);
+
         // 1. task is still doing, skip review this task
         if task.status == deploy_task::Status::Doing.to_string() {
+            // offline worker can not report result, set failed to retry when it is back
+            if !is_online {
+                deploy_task::set_failed_by_id(task.id, "Worker offline").await?;
+            }
+            doing_count += 1;
             continue;
         }
 
-        // 1.1 task is not doing, mean task is success or failed, must be done
-        done_count += 1;
-
         // 2. task is success
         if task.status == deploy_task::Status::Success.to_string() {
             debug!(
@@ -74,30 +97,60 @@ async fn handle_one(dp: &deployment::Model) -> Result<()> {
             continue;
         }
 
-        // 3. task is failed
-        if task.status == deploy_task::Status::Failed.to_string() {
-            debug!(
-                dp_id = dp.id,
-                ip = task.worker_ip,
-                task_id = dp.task_id,
-                "task failed: {}",
-                task.message,
-            );
-            failed_message.clone_from(&task.message);
+        // 3. task is failed, retry it with backoff if retries left
+        if is_online && task.retry_count < deploy_settings.max_retries {
+            let backoff = deploy_settings.retry_backoff(task.retry_count);
+            if (now - task.updated_at).num_seconds() >= backoff {
+                deploy_task::retry(task.id, task.retry_count + 1).await?;
+                info!(
+                    dp_id = dp.id,
+                    ip = task.worker_ip,
+                    "task retry {}: {}",
+                    task.retry_count + 1,
+                    task.message
+                );
+            }
+            doing_count += 1;
             continue;
         }
+        debug!(
+            dp_id = dp.id,
+            ip = task.worker_ip,
+            task_id = dp.task_id,
+            "task failed: {}",
+            task.message,
+        );
+        failed_count += 1;
+        failed_message = format!("{}: {}", task.worker_ip, task.message);
     }
-    // 4. if all tasks are done, update deploy status
-    if done_count == tasks.len() as i32 {
-        if done_count != success_count {
-            info!(dp_id = dp.id, "review failed: {:?}", failed_message);
-            set_failed(dp.id, dp.project_id, &failed_message).await?;
-            return Ok(());
-        }
-        info!(dp_id = dp.id, "review success");
-        set_success(dp.id, dp.project_id).await?;
-    } else {
+    let total_count = tasks.len() as i32;
+    deploys::set_counts(dp.id, success_count, failed_count, total_count).await?;
+
+    // 4. if all tasks are done, update deploy status by success policy
+    if doing_count > 0 {
         info!(dp_id = dp.id, "review not done");
+        return Ok(());
+    }
+    for task in tasks.iter() {
+        let msg = format!(
+            "Worker {}: {} {}",
+            task.worker_ip, task.status, task.message
+        );
+        buildlog::append(dp.id, msg.trim_end());
+    }
+    if !deploy_settings.is_success(success_count, total_count) {
+        info!(dp_id = dp.id, "review failed: {:?}", failed_message);
+        set_failed(dp, &failed_message).await?;
+        return Ok(());
+    }
+    info!(
+        dp_id = dp.id,
+        "review success, {}/{} workers", success_count, total_count
+    );
+    set_success(dp).await?;
+    // previous deployments are replaced by current one
+    if let Err(e) = super::cleanup::outdate_previous(dp).await {
+        warn!(dp_id = dp.id, "outdate previous failed: {:?}", e);
     }
     Ok(())
 }
@@ -0,0 +1,122 @@
+use super::{buildlog, waiting::deploy_to_workers};
+use anyhow::{anyhow, Result};
+use land_dao::{
+    deploys::{self, DeployType},
+    models::deployment,
+    projects, store, users,
+};
+use tracing::info;
+
+/// rollback creates a new deployment with the artifact of target deployment,
+/// it reuses the stored wasm file without compiling.
+/// Current deployment is set outdated after the new deployment is success.
+pub async fn rollback(project_id: i32, target_id: i32) -> Result<deployment::Model> {
+    let target = get_success_target(project_id, target_id).await?;
+    let deploy_type = if target.deploy_type == DeployType::Development.to_string() {
+        DeployType::Development
+    } else {
+        DeployType::Production
+    };
+    let message = format!("Rollback to deployment {}", target.id);
+    redeploy(project_id, &target, deploy_type, &message).await
+}
+
+/// promote deploys the artifact of a preview deployment to production domain
+pub async fn promote(project_id: i32, target_id: i32) -> Result<deployment::Model> {
+    let target = get_success_target(project_id, target_id).await?;
+    if target.deploy_type != DeployType::Development.to_string() {
+        return Err(anyhow!("Deployment is not a preview deployment"));
+    }
+    let message = format!("Promote preview deployment {}", target.id);
+    let dp = redeploy(project_id, &target, DeployType::Production, &message).await?;
+    // promoted deployment takes all traffic, stop canary
+    projects::set_canary(project_id, 0, 0).await?;
+    Ok(dp)
+}
+
+async fn get_success_target(project_id: i32, target_id: i32) -> Result<deployment::Model> {
+    let target = deploys::get_by_id(target_id).await?;
+    let target = target
+        .filter(|dp| dp.project_id == project_id)
+        .ok_or_else(|| anyhow!("Deployment not found"))?;
+    if target.deploy_status != deploys::Status::Success.to_string() {
+        return Err(anyhow!("Deployment is not success"));
+    }
+    Ok(target)
+}
+
+/// redeploy creates a new deployment of deploy_type with the artifact of target deployment
+async fn redeploy(
+    project_id: i32,
+    target: &deployment::Model,
+    deploy_type: DeployType,
+    message: &str,
+) -> Result<deployment::Model> {
+    let project = projects::get_by_id(project_id)
+        .await?
+        .ok_or_else(|| anyhow!("Project not found"))?;
+    if project.status == projects::Status::Disabled.to_string() {
+        return Err(anyhow!("Project is disabled"));
+    }
+    let storage_data = store::list_success_by_deploys(vec![target.id]).await?;
+    let storage_item = storage_data
+        .get(&target.id)
+        .ok_or_else(|| anyhow!("Deployment artifact not found"))?;
+    let user = users::get_by_id(project.owner_id, Some(users::UserStatus::Active))
+        .await?
+        .ok_or_else(|| anyhow!("User not found or not active"))?;
+
+    // create new deployment to the current domain of deploy type,
+    // it is uploading at once so waiting deployer does not build it again
+    let domain = match deploy_type {
+        DeployType::Development => projects::ensure_dev_domain(&project).await?,
+        DeployType::Production => project.prod_domain.clone(),
+    };
+    let dp = deploys::create(
+        &project,
+        user.uuid,
+        domain,
+        deploy_type.clone(),
+        target.playground_id,
+        &deploys::Spec::from_model(target),
+        deploys::Status::Uploading,
+    )
+    .await?;
+
+    // reuse artifact of target deployment
+    let record = store::create(
+        dp.owner_id,
+        dp.project_id,
+        dp.id,
+        &dp.task_id,
+        &storage_item.path,
+        &storage_item.file_hash,
+        storage_item.file_size,
+    )
+    .await?;
+    store::set_success(record.id, Some(storage_item.file_target.clone())).await?;
+    store::set_aot(
+        record.id,
+        &storage_item.aot_target,
+        &storage_item.aot_hash,
+        &storage_item.aot_engine,
+    )
+    .await?;
+    let record = store::get_success_by_deploy(dp.id)
+        .await?
+        .ok_or_else(|| anyhow!("Storage not found"))?;
+
+    if deploy_type == DeployType::Production {
+        projects::set_deploy_status(project.id, deploys::Status::Deploying, message).await?;
+    }
+    buildlog::append(dp.id, message);
+    deploy_to_workers(&dp, &record).await?;
+    info!(
+        project_id = project.id,
+        dp_id = dp.id,
+        target_id = target.id,
+        "Redeploy: {}",
+        message
+    );
+    Ok(dp)
+}
@@ -0,0 +1,122 @@
+use super::{
+    buildlog,
+    validate::validate,
+    waiting::{deploy_to_workers, set_failed},
+};
+use anyhow::{anyhow, Result};
+use land_dao::{
+    deploys::{self, DeployType},
+    models::deployment,
+    projects, store, users,
+};
+use std::time::Instant;
+use tracing::info;
+
+/// MAX_UPLOAD_SIZE is the max size of uploaded wasm component, 20MB
+pub const MAX_UPLOAD_SIZE: usize = 20 * 1024 * 1024;
+
+/// upload deploys a prebuilt wasm component to a blank project,
+/// it validates the component with smoke test, saves it to storage and distributes it to workers
+pub async fn upload(
+    project_id: i32,
+    data: Vec<u8>,
+    deploy_type: DeployType,
+) -> Result<deployment::Model> {
+    let project = projects::get_by_id(project_id)
+        .await?
+        .ok_or_else(|| anyhow!("Project not found"))?;
+    if project.status == projects::Status::Disabled.to_string() {
+        return Err(anyhow!("Project is disabled"));
+    }
+    if project.created_by != projects::CreatedBy::Blank.to_string() {
+        return Err(anyhow!("Only blank project supports uploading wasm"));
+    }
+    if data.is_empty() {
+        return Err(anyhow!("Wasm file is empty"));
+    }
+    if data.len() > MAX_UPLOAD_SIZE {
+        return Err(anyhow!(
+            "Wasm file is too large, max size is {} bytes",
+            MAX_UPLOAD_SIZE
+        ));
+    }
+
+    let user = users::get_by_id(project.owner_id, Some(users::UserStatus::Active))
+        .await?
+        .ok_or_else(|| anyhow!("User not found or not active"))?;
+    let domain = match deploy_type {
+        DeployType::Development => projects::ensure_dev_domain(&project).await?,
+        DeployType::Production => project.prod_domain.clone(),
+    };
+    let dp = deploys::create(
+        &project,
+        user.uuid,
+        domain,
+        deploy_type.clone(),
+        0,
+        &deploys::Spec::default(),
+        // create as compiling to validate component, waiting deployer never picks it up
+        deploys::Status::Compiling,
+    )
+    .await?;
+    buildlog::append(
+        dp.id,
+        &format!("Upload wasm component, size: {} bytes", data.len()),
+    );
+
+    // validate component and run smoke test, block deploying if failed
+    if let Err(e) = validate(&dp, &data).await {
+        set_failed(&dp, &e.to_string()).await?;
+        return Err(e);
+    }
+
+    // set uploading
+    deploys::set_deploy_status(dp.id, deploys::Status::Uploading, "Uploading").await?;
+    if deploy_type == DeployType::Production {
+        projects::set_deploy_status(project.id, deploys::Status::Uploading, "Uploading").await?;
+    }
+
+    // save file to storage
+    let st = Instant::now();
+    let now_text = chrono::Utc::now().format("%Y%m%d%H%M%S").to_string();
+    let file_name = format!("{}/{}_{}.wasm", project.uuid, dp.domain, now_text);
+    let file_hash = format!("{:x}", md5::compute(&data));
+    let file_size = data.len() as i32;
+    let record = store::create(
+        dp.owner_id,
+        dp.project_id,
+        dp.id,
+        &dp.task_id,
+        &file_name,
+        &file_hash,
+        file_size,
+    )
+    .await?;
+    super::aot::precompile(&dp, &record, &data).await;
+    if let Err(e) = crate::storage::save(&file_name, data).await {
+        set_failed(&dp, &format!("Save wasm failed: {}", e)).await?;
+        return Err(e);
+    }
+    let target_url = crate::storage::build_url(&file_name).await?;
+    store::set_success(record.id, Some(target_url)).await?;
+    buildlog::append(
+        dp.id,
+        &format!(
+            "Upload done in {}ms, size: {} bytes",
+            st.elapsed().as_millis(),
+            file_size
+        ),
+    );
+
+    let storage_item = store::get_success_by_deploy(dp.id)
+        .await?
+        .ok_or_else(|| anyhow!("Storage not found"))?;
+    deploy_to_workers(&dp, &storage_item).await?;
+    info!(
+        project_id = project.id,
+        dp_id = dp.id,
+        size = file_size,
+        "Upload wasm component"
+    );
+    Ok(dp)
+}
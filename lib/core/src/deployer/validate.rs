@@ -0,0 +1,83 @@
+use super::buildlog;
+use anyhow::{anyhow, Result};
+use land_dao::{deploys, envs, models::deployment};
+use land_wasm_host::{hostcall, Context, Limits, Worker};
+use std::time::Instant;
+use tracing::{debug, warn};
+
+/// SMOKE_WALL_TIME is the max wall time of smoke test request
+const SMOKE_WALL_TIME: u64 = 3;
+
+/// validate loads the wasm component to check imports and exports,
+/// then runs a smoke test request `GET /` to catch traps before deploying to workers.
+/// Deploying always sends this real `GET /` request to the guest in land-server.
+/// Untrusted guest runs in land-server, so it gets plain envs only without secrets,
+/// and outbound fetch is denied.
+pub(crate) async fn validate(dp: &deployment::Model, data: &[u8]) -> Result<()> {
+    // 1. load component, it checks land:worker/http-handler world
+    let st = Instant::now();
+    let worker = Worker::from_binary(data, Some(format!("deploy-{}", dp.id)))
+        .await
+        .map_err(|e| anyhow!("Invalid wasm component: {:#}", e))?;
+    buildlog::append(
+        dp.id,
+        &format!(
+            "Validate wasm component done in {}ms",
+            st.elapsed().as_millis()
+        ),
+    );
+
+    // 2. run smoke test request with deployment limits and short wall time
+    let st = Instant::now();
+    let spec = deploys::Spec::from_model(dp);
+    let limits = Limits::new(
+        spec.cpu_time_limit.map(|v| v as u64),
+        spec.memory_limit.map(|v| v as u64),
+        Some(SMOKE_WALL_TIME),
+        None,
+    );
+    // guest reads plain envs of project, secrets are not exposed to smoke test
+    let envs = envs::list_by_project(dp.project_id)
+        .await?
+        .into_iter()
+        .filter(|m| !m.is_secret)
+        .map(|m| (m.env_key, m.env_value))
+        .collect();
+    let mut context = Context::new(Some(envs));
+    context.set_limits(limits);
+    // project egress policy is not loaded in land-server, deny all fetch requests
+    context.host_ctx().set_fetch_limit(Some(0));
+    context.host_ctx().set_deploy_id(dp.id.to_string());
+    let req = hostcall::Request {
+        method: "GET".to_string(),
+        uri: format!("http://{}/", dp.domain),
+        headers: vec![("user-agent".to_string(), "land-smoke-test".to_string())],
+        body: Some(0),
+    };
+    let logs = context.logs();
+    let result = worker.handle_request(req, context).await;
+    // guest output helps to find out why smoke test fails
+    for line in logs.take() {
+        buildlog::append(dp.id, &format!("[{}] {}", line.stream, line.message));
+    }
+    let (resp, _) = result.map_err(|e| anyhow!("Smoke test GET / failed: {:#}", e))?;
+    debug!(dp_id = dp.id, "Smoke test status: {}", resp.status);
+    buildlog::append(
+        dp.id,
+        &format!(
+            "Smoke test GET / done in {}ms, status: {}",
+            st.elapsed().as_millis(),
+            resp.status
+        ),
+    );
+    Ok(())
+}
+
+/// init_validate initializes wasm engines to run smoke test and precompile aot,
+/// epoch ticks are required to stop guest by cpu time and wall time.
+/// Default engine settings are used, workers with different settings compile aot locally.
+pub(crate) fn init_validate() {
+    if let Err(e) = land_wasm_host::init_engines(Default::default()) {
+        warn!("deployer init wasm engines error: {:?}", e);
+    }
+}
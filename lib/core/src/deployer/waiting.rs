@@ -1,16 +1,44 @@
+use super::buildlog;
 use crate::agent::Item;
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use land_dao::{
     deploy_task,
     deploys::{self, Status},
-    models::deployment,
+    models::{deployment, storage},
     playground, projects, settings, store, workers,
 };
+use lazy_static::lazy_static;
+use once_cell::sync::OnceCell;
+use std::{
+    collections::HashSet,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::sync::Semaphore;
 use tracing::{debug, info, instrument, warn};
 
-/// init_waiting starts handling waiting deploy tasks
-pub async fn init_waiting() {
-    debug!("deployer init_waiting");
+lazy_static! {
+    /// BUILDING is the deployments building in pool
+    static ref BUILDING: Mutex<HashSet<i32>> = Mutex::new(HashSet::new());
+}
+
+static BUILD_POOL: OnceCell<Arc<Semaphore>> = OnceCell::new();
+static BUILD_TIMEOUT: OnceCell<Duration> = OnceCell::new();
+
+/// init_waiting starts handling waiting deploy tasks,
+/// it builds deployments concurrently with timeout
+pub async fn init_waiting(concurrency: usize, timeout_secs: u64) {
+    debug!(
+        "deployer init_waiting, concurrency: {}, timeout: {}s",
+        concurrency, timeout_secs
+    );
+    BUILD_POOL
+        .set(Arc::new(Semaphore::new(concurrency.max(1))))
+        .unwrap();
+    BUILD_TIMEOUT
+        .set(Duration::from_secs(timeout_secs.max(1)))
+        .unwrap();
+    super::validate::init_validate();
     tokio::spawn(async move {
         let mut ticker = tokio::time::interval(std::time::Duration::from_secs(1));
         ticker.tick().await;
@@ -26,22 +54,43 @@ pub async fn init_waiting() {
     });
 }
 
+/// is_production checks if the deployment is a production deployment,
+/// only production deployment updates project deploy status
+fn is_production(dp: &deployment::Model) -> bool {
+    dp.deploy_type == deploys::DeployType::Production.to_string()
+}
+
 /// set_failed sets the deploy and project status to failed
-pub(crate) async fn set_failed(dp_id: i32, project_id: i32, mut message: &str) -> Result<()> {
-    // find last \n flag in 255 chars in message
+pub(crate) async fn set_failed(dp: &deployment::Model, mut message: &str) -> Result<()> {
+    // full message is kept in build log
+    buildlog::append(dp.id, &format!("Failed: {}", message));
+    buildlog::finish(dp).await;
+    // keep 255 bytes at most, cut at char boundary
     if message.len() > 255 {
-        message = &message[..255];
+        let end = message
+            .char_indices()
+            .map(|(i, c)| i + c.len_utf8())
+            .take_while(|end| *end <= 255)
+            .last()
+            .unwrap_or(0);
+        message = &message[..end];
     }
-    deploys::set_deploy_status(dp_id, deploys::Status::Failed, message).await?;
-    projects::set_deploy_status(project_id, deploys::Status::Failed, message).await?;
-    warn!(dp_id = dp_id, "set failed: {}", message);
+    deploys::set_deploy_status(dp.id, deploys::Status::Failed, message).await?;
+    if is_production(dp) {
+        projects::set_deploy_status(dp.project_id, deploys::Status::Failed, message).await?;
+    }
+    warn!(dp_id = dp.id, "set failed: {}", message);
     Ok(())
 }
 
 /// set_success sets the deploy and projectstatus to success
-pub(crate) async fn set_success(dp_id: i32, project_id: i32) -> Result<()> {
-    deploys::set_deploy_status(dp_id, deploys::Status::Success, "Success").await?;
-    projects::set_deploy_status(project_id, deploys::Status::Success, "Success").await?;
+pub(crate) async fn set_success(dp: &deployment::Model) -> Result<()> {
+    buildlog::append(dp.id, "Success");
+    buildlog::finish(dp).await;
+    deploys::set_deploy_status(dp.id, deploys::Status::Success, "Success").await?;
+    if is_production(dp) {
+        projects::set_deploy_status(dp.project_id, deploys::Status::Success, "Success").await?;
+    }
     Ok(())
 }
 
@@ -53,14 +102,33 @@ async fn handle() -> Result<()> {
         return Ok(());
     }
     info!("Waitings: {}", deploy_data.len());
-    for dp in deploy_data.iter() {
-        match handle_one(dp).await {
-            Ok(_) => {}
-            Err(e) => {
-                set_failed(dp.id, dp.project_id, e.to_string().as_str()).await?;
+    let pool = BUILD_POOL.get().unwrap();
+    let mut position = 0;
+    for dp in deploy_data.into_iter() {
+        // re-queued deployment may be still building
+        if BUILDING.lock().unwrap().contains(&dp.id) {
+            continue;
+        }
+        let Ok(permit) = pool.clone().try_acquire_owned() else {
+            // pool is full, report queue position
+            position += 1;
+            let message = format!("Waiting in build queue, position {}", position);
+            if dp.deploy_message != message {
+                deploys::set_deploy_status(dp.id, Status::WaitDeploy, &message).await?;
+            }
+            continue;
+        };
+        BUILDING.lock().unwrap().insert(dp.id);
+        tokio::spawn(async move {
+            if let Err(e) = handle_one(&dp).await {
                 warn!(dp_id = dp.id, "deployer waiting handle error: {:?}", e);
+                if let Err(e) = set_failed(&dp, e.to_string().as_str()).await {
+                    warn!(dp_id = dp.id, "deployer set failed error: {:?}", e);
+                }
             }
-        }
+            BUILDING.lock().unwrap().remove(&dp.id);
+            drop(permit);
+        });
     }
     Ok(())
 }
@@ -71,24 +139,44 @@ async fn handle_one(dp: &deployment::Model) -> Result<()> {
     // 1. get project
     let project = projects::get_by_id(dp.project_id).await?;
     if project.is_none() {
-        return set_failed(dp.id, dp.project_id, "Project not found").await;
+        return set_failed(dp, "Project not found").await;
     }
     let project = project.unwrap();
+    if project.status == projects::Status::Disabled.to_string() {
+        return set_failed(dp, "Project is disabled").await;
+    }
 
-    // 2. if project is not created by playground, currently only playground can create project
+    // 2. blank project deploys uploaded wasm, no need to compile.
+    // it may be re-queued by watchdog, so redistribute the uploaded artifact
+    if project.created_by == projects::CreatedBy::Blank.to_string() {
+        let Some(storage_item) = store::get_success_by_deploy(dp.id).await? else {
+            return set_failed(dp, "Wasm component not uploaded").await;
+        };
+        return deploy_to_workers(dp, &storage_item).await;
+    }
     if project.created_by != projects::CreatedBy::Playground.to_string() {
-        return set_failed(dp.id, dp.project_id, "Project not created by playground").await;
+        return set_failed(dp, "Project not created by playground").await;
     }
 
-    // 3. get playground
-    let playground = playground::get_by_project(dp.project_id).await?;
+    // 3. get playground source of the deployment, not the latest one,
+    // a later preview deployment may have saved newer source.
+    // deployments created before playground_id is recorded use the latest one
+    let playground = if dp.playground_id > 0 {
+        playground::get_by_id(dp.playground_id).await?
+    } else {
+        playground::get_by_project(dp.project_id).await?
+    };
     if playground.is_none() {
-        return set_failed(dp.id, dp.project_id, "Playground not found").await;
+        return set_failed(dp, "Playground not found").await;
     }
     let playground = playground.unwrap();
 
     // 4. set compiling
     deploys::set_deploy_status(dp.id, deploys::Status::Compiling, "Compiling").await?;
+    buildlog::append(
+        dp.id,
+        &format!("Build started, playground: {}", playground.id),
+    );
 
     // 5. write source code to file
     let dir = tempfile::Builder::new().prefix("runtime-land").tempdir()?;
@@ -108,20 +196,35 @@ async fn handle_one(dp: &deployment::Model) -> Result<()> {
     let target_wasm = dir
         .path()
         .join(format!("{}_{}.wasm", playground.project_id, playground.id));
-    land_wasm_gen::componentize_js(
-        source_js.to_str().unwrap(),
-        target_wasm.to_str().unwrap(),
-        None,
-    )?;
+    let opts = land_wasm_gen::BuildOpts {
+        timeout: BUILD_TIMEOUT.get().cloned(),
+        logger: Some(buildlog::logger(dp.id)),
+        ..Default::default()
+    };
+    let (src, target) = (source_js.clone(), target_wasm.clone());
+    // build in blocking thread, it waits wizer process
+    tokio::task::spawn_blocking(move || {
+        land_wasm_gen::componentize_js_with(
+            src.to_str().unwrap(),
+            target.to_str().unwrap(),
+            None,
+            &opts,
+        )
+    })
+    .await??;
     debug!("Compile success: {:?}", target_wasm);
 
-    // 7. set uploading
+    // 7. validate component and run smoke test, block deploying if failed
+    let file_data = std::fs::read(&target_wasm)?;
+    super::validate::validate(dp, &file_data).await?;
+
+    // 8. set uploading
     deploys::set_deploy_status(dp.id, deploys::Status::Uploading, "Uploading").await?;
+    let st = Instant::now();
 
-    // 8. create storage record
+    // 9. create storage record
     let now_text = chrono::Utc::now().format("%Y%m%d%H%M%S").to_string();
     let file_name = format!("{}/{}_{}.wasm", project.uuid, dp.domain, now_text);
-    let file_data = std::fs::read(&target_wasm)?;
     let file_hash = format!("{:x}", md5::compute(&file_data));
     let file_size = file_data.len() as i32;
     let storage_record = store::create(
@@ -136,36 +239,70 @@ async fn handle_one(dp: &deployment::Model) -> Result<()> {
     .await?;
     debug!("Create storage record: {:?}", storage_record);
 
-    // 9. save file to storage
+    // 10. precompile aot artifact for workers
+    super::aot::precompile(dp, &storage_record, &file_data).await;
+
+    // 11. save file to storage
     debug!("Save file to storage begin: {:?}", file_name);
     crate::storage::save(&file_name, file_data).await?;
     debug!("Save file to storage end: {:?}", file_name);
     let target_url = crate::storage::build_url(&file_name).await?;
     debug!("Save file to storage url: {:?}", target_url);
     store::set_success(storage_record.id, Some(target_url.clone())).await?;
+    buildlog::append(
+        dp.id,
+        &format!(
+            "Upload done in {}ms, size: {} bytes",
+            st.elapsed().as_millis(),
+            file_size
+        ),
+    );
+
+    // 12. create deploy tasks for online workers
+    let storage_item = store::get_success_by_deploy(dp.id)
+        .await?
+        .ok_or_else(|| anyhow!("Storage not found"))?;
+    deploy_to_workers(dp, &storage_item).await
+}
 
-    // 10. create details task for each worker
+/// deploy_to_workers creates deploy tasks of wasm file for each online worker,
+/// and sets deployment deploying to trigger review logic
+pub(crate) async fn deploy_to_workers(
+    dp: &deployment::Model,
+    storage_item: &storage::Model,
+) -> Result<()> {
+    // 1. get online workers
     let workers_value = workers::find_all(Some(workers::Status::Online)).await?;
     if workers_value.is_empty() {
         warn!(dp_id = dp.id, "No worker online");
-        return set_failed(dp.id, dp.project_id, "No worker online").await;
+        return set_failed(dp, "No worker online").await;
     }
 
-    // 11. create conf values
+    // 2. create conf values
     let domain_settings = settings::get_domain_settings().await?;
     let item = Item {
         user_id: dp.owner_id,
         project_id: dp.project_id,
         deploy_id: dp.id,
         task_id: dp.task_id.clone(),
-        file_name,
-        file_hash,
-        download_url: target_url,
+        file_name: storage_item.path.clone(),
+        file_hash: storage_item.file_hash.clone(),
+        download_url: storage_item.file_target.clone(),
+        aot_url: storage_item.aot_target.clone(),
+        aot_hash: storage_item.aot_hash.clone(),
+        aot_engine: storage_item.aot_engine.clone(),
         domain: format!("{}.{}", dp.domain, domain_settings.domain_suffix),
+        spec: deploys::Spec::from_model(dp),
+        // envs are synced by confs, not saved in task content
+        envs: Default::default(),
+        // canary weight and inspect are synced by confs too
+        canary_weight: 0,
+        inspect: false,
+        preview: dp.deploy_type == deploys::DeployType::Development.to_string(),
     };
     let item_content = serde_json::to_string(&item)?;
 
-    // 12. create details task for each worker
+    // 3. create details task for each worker
     let mut rips = vec![];
     for worker in workers_value.iter() {
         let task = deploy_task::create(
@@ -180,7 +317,12 @@ async fn handle_one(dp: &deployment::Model) -> Result<()> {
         rips.push(worker.ip.clone());
     }
 
-    // 13. update deployment status, to trigger review logic
+    buildlog::append(
+        dp.id,
+        &format!("Distribute to {} workers: {}", rips.len(), rips.join(",")),
+    );
+
+    // 4. update deployment status, to trigger review logic
     deploys::set_rips(dp.id, rips.join(","), rips.len() as i32).await?;
     deploys::set_deploy_status(dp.id, deploys::Status::Deploying, "Deploying").await?;
 
@@ -0,0 +1,118 @@
+use super::waiting::set_failed;
+use anyhow::Result;
+use land_dao::{
+    deploy_task, deploys,
+    deploys::Status,
+    settings::{self, DeploySettings},
+    workers,
+};
+use std::collections::{HashMap, HashSet};
+use tracing::{debug, info, instrument, warn};
+
+/// REQUEUE_TIMES is how many compile timeouts a deployment can be re-queued before failed
+const REQUEUE_TIMES: i64 = 3;
+
+/// init_watchdog starts checking stale deployments and orphaned tasks
+pub async fn init_watchdog() {
+    debug!("deployer init_watchdog");
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(std::time::Duration::from_secs(30));
+        ticker.tick().await;
+        loop {
+            match watch().await {
+                Ok(_) => {}
+                Err(e) => {
+                    warn!("deployer watchdog handle error: {:?}", e);
+                }
+            };
+            ticker.tick().await;
+        }
+    });
+}
+
+#[instrument("[DEPLOY-WATCHDOG]")]
+async fn watch() -> Result<()> {
+    let deploy_settings = settings::get_deploy_settings().await?;
+    check_stale(&deploy_settings).await?;
+    clean_orphan_tasks().await
+}
+
+/// check_stale re-queues or fails deployments stuck in compiling, uploading or deploying
+async fn check_stale(deploy_settings: &DeploySettings) -> Result<()> {
+    let now = chrono::Utc::now().naive_utc();
+    let compile_timeout = deploy_settings.compile_timeout_secs.max(60);
+    let deploy_timeout = deploy_settings.deploy_timeout_secs.max(60);
+    for status in [Status::Compiling, Status::Uploading, Status::Deploying] {
+        let deploy_data = deploys::list_by_deploy_status(status.clone()).await?;
+        for dp in deploy_data.iter() {
+            let stale_secs = (now - dp.updated_at).num_seconds();
+            if status == Status::Deploying {
+                if stale_secs < deploy_timeout {
+                    continue;
+                }
+                let message = format!("Timeout in {} after {}s", status, stale_secs);
+                set_failed(dp, &message).await?;
+                continue;
+            }
+            if stale_secs < compile_timeout {
+                continue;
+            }
+            // compiling is interrupted by server restart mostly, compile it again
+            let age_secs = (now - dp.created_at).num_seconds();
+            if age_secs < compile_timeout * REQUEUE_TIMES {
+                let message = format!("Re-queued after {} timeout", status);
+                deploys::set_deploy_status(dp.id, Status::WaitDeploy, &message).await?;
+                info!(dp_id = dp.id, "Re-queue stale deployment: {}", status);
+                continue;
+            }
+            let message = format!("Timeout in {} after {}s", status, stale_secs);
+            set_failed(dp, &message).await?;
+        }
+    }
+    Ok(())
+}
+
+/// clean_orphan_tasks fails doing tasks of failed or missing deployments,
+/// and deletes tasks of removed workers
+async fn clean_orphan_tasks() -> Result<()> {
+    let tasks = deploy_task::list(None, Some(deploy_task::Status::Doing), None).await?;
+    if tasks.is_empty() {
+        return Ok(());
+    }
+    let worker_ips: HashSet<String> = workers::find_all(None)
+        .await?
+        .into_iter()
+        .map(|w| w.ip)
+        .collect();
+    let deploy_ids = tasks.iter().map(|t| t.deploy_id).collect();
+    let deploy_data: HashMap<i32, String> = deploys::list_by_ids(deploy_ids)
+        .await?
+        .into_iter()
+        .map(|dp| (dp.id, dp.deploy_status))
+        .collect();
+
+    let mut removed_ids = vec![];
+    for task in tasks.iter() {
+        if !worker_ips.contains(&task.worker_ip) {
+            removed_ids.push(task.id);
+            continue;
+        }
+        let failed = match deploy_data.get(&task.deploy_id) {
+            Some(status) => *status == Status::Failed.to_string(),
+            None => true,
+        };
+        if failed {
+            deploy_task::set_failed_by_id(task.id, "Deployment is failed or removed").await?;
+            info!(
+                task_id = task.task_id,
+                ip = task.worker_ip,
+                "Fail orphan task"
+            );
+        }
+    }
+    if !removed_ids.is_empty() {
+        let count = deploy_task::delete_by_ids(removed_ids).await?;
+        info!("Delete tasks of removed workers: {}", count);
+    }
+    Ok(())
+}
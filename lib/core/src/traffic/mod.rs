@@ -4,7 +4,7 @@ use serde::{Deserialize, Serialize};
 
 mod promql;
 mod query;
-pub use query::{flow_traffic, projects_traffic, requests_traffic};
+pub use query::{cpu_traffic, flow_traffic, latency_traffic, projects_traffic, requests_traffic};
 
 #[derive(Serialize, Deserialize, Default, Debug)]
 pub struct Settings {
@@ -32,6 +32,65 @@ pub(crate) fn flow_ql(pid: Option<String>, uid: Option<String>, step: &str) -> R
     }
 }
 
+pub(crate) fn cpu_ql(pid: Option<String>, uid: Option<String>, step: &str) -> Result<String> {
+    if let Some(pid) = pid {
+        Ok(format!(
+            "sum(increase(req_fn_cpu_ms_total{{pid=\"{}\"}}[{}]))",
+            pid, step
+        ))
+    } else if let Some(uid) = uid {
+        Ok(format!(
+            "sum(increase(req_fn_cpu_ms_total{{uid=\"{}\"}}[{}]))",
+            uid, step
+        ))
+    } else {
+        Ok(format!("sum(increase(req_fn_cpu_ms_total[{}]))", step))
+    }
+}
+
+pub(crate) fn latency_ql(
+    pid: Option<String>,
+    uid: Option<String>,
+    step: &str,
+    quantile: f64,
+) -> Result<String> {
+    if let Some(pid) = pid {
+        Ok(format!(
+            "histogram_quantile({}, sum by (le) (rate(req_fn_duration_ms_bucket{{pid=\"{}\"}}[{}])))",
+            quantile, pid, step
+        ))
+    } else if let Some(uid) = uid {
+        Ok(format!(
+            "histogram_quantile({}, sum by (le) (rate(req_fn_duration_ms_bucket{{uid=\"{}\"}}[{}])))",
+            quantile, uid, step
+        ))
+    } else {
+        Ok(format!(
+            "histogram_quantile({}, sum by (le) (rate(req_fn_duration_ms_bucket[{}])))",
+            quantile, step
+        ))
+    }
+}
+
+pub(crate) fn start_ql(pid: Option<String>, uid: Option<String>, step: &str) -> Result<String> {
+    if let Some(pid) = pid {
+        Ok(format!(
+            "sum by (typ) (increase(req_fn_start_total{{pid=\"{}\"}}[{}]))",
+            pid, step
+        ))
+    } else if let Some(uid) = uid {
+        Ok(format!(
+            "sum by (typ) (increase(req_fn_start_total{{uid=\"{}\"}}[{}]))",
+            uid, step
+        ))
+    } else {
+        Ok(format!(
+            "sum by (typ) (increase(req_fn_start_total[{}]))",
+            step
+        ))
+    }
+}
+
 pub(crate) fn projects_traffic_ql(uid: Option<String>, pids: Vec<String>, step: &str) -> String {
     if let Some(uid) = uid {
         format!(
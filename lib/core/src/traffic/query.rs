@@ -5,7 +5,7 @@ use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use tracing::debug;
 use std::{collections::HashMap, sync::Once};
-use crate::traffic::promql::{flow_ql, projects_flows_ql, request_ql};
+use crate::traffic::promql::{cpu_ql, flow_ql, latency_ql, projects_flows_ql, request_ql, start_ql};
 use super::{promql::projects_traffic_ql, PeriodParams, Settings, SETTINGS_KEY};
 
 static CLIENT: OnceCell<Client> = OnceCell::new();
@@ -75,6 +75,50 @@ pub async fn flow_traffic(
     traffic_internal(period, query).await
 }
 
+/// cpu_traffic queries cpu time in milliseconds
+pub async fn cpu_traffic(
+    pid: Option<String>,
+    uid: Option<String>,
+    period: &PeriodParams,
+) -> Result<MultiLineSeries> {
+    let query = cpu_ql(pid, uid, &period.step_word)?;
+    debug!(
+        "query-cpu: {}, start:{}, end:{}, step:{}",
+        query, period.start, period.end, period.step
+    );
+    traffic_internal(period, query).await
+}
+
+/// latency_traffic queries p50, p95 and p99 latency in milliseconds,
+/// and cold and warm starts count
+pub async fn latency_traffic(
+    pid: Option<String>,
+    uid: Option<String>,
+    period: &PeriodParams,
+) -> Result<MultiLineSeries> {
+    let mut lines = MultiLineSeries::new();
+    for (key, quantile) in [("p50", 0.5), ("p95", 0.95), ("p99", 0.99)] {
+        let query = latency_ql(pid.clone(), uid.clone(), &period.step_word, quantile)?;
+        debug!(
+            "query-latency: {}, start:{}, end:{}, step:{}",
+            query, period.start, period.end, period.step
+        );
+        // quantile query has no labels, the series key is "metric"
+        let mut res = traffic_internal(period, query).await?;
+        if let Some(series) = res.remove("metric") {
+            lines.insert(key.to_string(), series);
+        }
+    }
+    let query = start_ql(pid, uid, &period.step_word)?;
+    debug!(
+        "query-starts: {}, start:{}, end:{}, step:{}",
+        query, period.start, period.end, period.step
+    );
+    let starts = traffic_internal(period, query).await?;
+    lines.extend(starts);
+    Ok(lines)
+}
+
 /// QueryParams is the parameters for querying range
 #[derive(Serialize, Debug)]
 pub struct Params {
@@ -0,0 +1,46 @@
+use crate::{
+    models::{deploy_log, deployment},
+    now_time, DB,
+};
+use anyhow::Result;
+use sea_orm::{
+    sea_query::Expr, ActiveModelTrait, ColumnTrait, EntityTrait, IntoActiveModel, QueryFilter,
+};
+
+/// save saves build log of a deployment, it replaces existing log
+pub async fn save(dp: &deployment::Model, content: &str) -> Result<()> {
+    let db = DB.get().unwrap();
+    let now = now_time();
+    if get_by_deploy(dp.id).await?.is_some() {
+        deploy_log::Entity::update_many()
+            .col_expr(deploy_log::Column::Content, Expr::value(content))
+            .col_expr(deploy_log::Column::UpdatedAt, Expr::value(now))
+            .filter(deploy_log::Column::DeployId.eq(dp.id))
+            .exec(db)
+            .await?;
+        return Ok(());
+    }
+    let model = deploy_log::Model {
+        id: 0,
+        owner_id: dp.owner_id,
+        project_id: dp.project_id,
+        deploy_id: dp.id,
+        content: content.to_string(),
+        created_at: now,
+        updated_at: now,
+    };
+    let mut active_model = model.into_active_model();
+    active_model.id = Default::default();
+    active_model.insert(db).await?;
+    Ok(())
+}
+
+/// get_by_deploy gets build log of a deployment
+pub async fn get_by_deploy(deploy_id: i32) -> Result<Option<deploy_log::Model>> {
+    let db = DB.get().unwrap();
+    let model = deploy_log::Entity::find()
+        .filter(deploy_log::Column::DeployId.eq(deploy_id))
+        .one(db)
+        .await?;
+    Ok(model)
+}
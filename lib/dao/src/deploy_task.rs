@@ -8,10 +8,13 @@ use sea_orm::{
     QueryOrder,
 };
 
-#[derive(strum::Display)]
+#[derive(strum::Display, Clone)]
 #[strum(serialize_all = "lowercase")]
 pub enum TaskType {
-    DeployWasmToWorker, // deploy wasm to worker
+    DeployWasmToWorker,         // deploy wasm to worker
+    RemoveDeploymentFromWorker, // remove routing, cached instance and artifacts from worker
+    DisableDeploymentOnWorker,  // remove routing and cached instance, keep artifacts
+    EnableDeploymentOnWorker,   // restore routing and cached instance
 }
 
 #[derive(strum::Display)]
@@ -31,12 +34,18 @@ pub async fn create(
     worker_ip: &str,
 ) -> Result<deploy_task::Model> {
     let now = now_time();
+    // deploy task uses deployment task id to review deploying result,
+    // other tasks use unique task id to report status separately
+    let task_id = match task_type {
+        TaskType::DeployWasmToWorker => dp.task_id.clone(),
+        _ => format!("{}-{}", task_type, uuid::Uuid::new_v4()),
+    };
     let model = deploy_task::Model {
         id: 0,
         owner_id: dp.owner_id,
         project_id: dp.project_id,
         deploy_id: dp.id,
-        task_id: dp.task_id.clone(),
+        task_id,
         task_type: task_type.to_string(),
         task_content: task_content.to_string(),
         worker_id,
@@ -45,6 +54,7 @@ pub async fn create(
         created_at: now,
         updated_at: now,
         message: "".to_string(),
+        retry_count: 0,
     };
     let mut active_model = model.into_active_model();
     active_model.id = Default::default();
@@ -74,6 +84,59 @@ pub async fn list(
     Ok(models)
 }
 
+/// list_by_task_ids lists deploy tasks by task ids
+pub async fn list_by_task_ids(task_ids: Vec<String>) -> Result<Vec<deploy_task::Model>> {
+    let db = DB.get().unwrap();
+    let models = deploy_task::Entity::find()
+        .filter(deploy_task::Column::TaskId.is_in(task_ids))
+        .order_by_asc(deploy_task::Column::Id)
+        .all(db)
+        .await?;
+    Ok(models)
+}
+
+/// retry sets failed task doing again and increases retry count
+pub async fn retry(id: i32, retry_count: i32) -> Result<()> {
+    let db = DB.get().unwrap();
+    deploy_task::Entity::update_many()
+        .col_expr(
+            deploy_task::Column::Status,
+            Expr::value(Status::Doing.to_string()),
+        )
+        .col_expr(deploy_task::Column::RetryCount, Expr::value(retry_count))
+        .col_expr(deploy_task::Column::UpdatedAt, Expr::value(now_time()))
+        .filter(deploy_task::Column::Id.eq(id))
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
+/// set_failed_by_id set task failed by id
+pub async fn set_failed_by_id(id: i32, message: &str) -> Result<()> {
+    let db = DB.get().unwrap();
+    deploy_task::Entity::update_many()
+        .col_expr(
+            deploy_task::Column::Status,
+            Expr::value(Status::Failed.to_string()),
+        )
+        .col_expr(deploy_task::Column::UpdatedAt, Expr::value(now_time()))
+        .col_expr(deploy_task::Column::Message, Expr::value(message))
+        .filter(deploy_task::Column::Id.eq(id))
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
+/// delete_by_ids deletes deploy tasks by ids
+pub async fn delete_by_ids(ids: Vec<i32>) -> Result<u64> {
+    let db = DB.get().unwrap();
+    let res = deploy_task::Entity::delete_many()
+        .filter(deploy_task::Column::Id.is_in(ids))
+        .exec(db)
+        .await?;
+    Ok(res.rows_affected)
+}
+
 /// set_success set task success
 pub async fn set_success(ip: String, task_id: String) -> Result<()> {
     let db = DB.get().unwrap();
@@ -1,4 +1,7 @@
-use crate::{models::deployment, now_time, DB};
+use crate::{
+    models::{deployment, project},
+    now_time, DB,
+};
 use anyhow::Result;
 use sea_orm::{
     sea_query::Expr, ActiveModelTrait, ColumnTrait, EntityTrait, IntoActiveModel, QueryFilter,
@@ -6,7 +9,7 @@ use sea_orm::{
 };
 use serde::{Deserialize, Serialize};
 
-#[derive(strum::Display)]
+#[derive(strum::Display, Clone, PartialEq)]
 #[strum(serialize_all = "lowercase")]
 pub enum Status {
     WaitDeploy,
@@ -26,55 +29,68 @@ pub enum DeploymentStatus {
     Outdated, // if a deployment is outdated, it will be deleted
 }
 
-#[derive(strum::Display)]
+#[derive(strum::Display, strum::EnumString, Clone, PartialEq)]
 #[strum(serialize_all = "lowercase")]
 pub enum DeployType {
     Production,  // production deployment
     Development, // development deployment
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+/// Spec is runtime limits of a deployment, unset limit means unlimited
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct Spec {
-    cpu_time_limit: Option<i32>,
-    memory_limit: Option<i32>,
-    wall_time_limit: Option<i32>,
-    fetch_limit: Option<i32>,
-}
-
-impl Default for Spec {
-    fn default() -> Self {
-        Self {
-            cpu_time_limit: Some(100), // 100ms
-            memory_limit: Some(128),   // 128MB
-            wall_time_limit: Some(30), // 30 seconds
-            fetch_limit: Some(5),      // send 5 requests
-        }
+    pub cpu_time_limit: Option<i32>,
+    pub memory_limit: Option<i32>,
+    pub wall_time_limit: Option<i32>,
+    pub fetch_limit: Option<i32>,
+    /// hosts or CIDRs that outbound fetch is allowed to, empty means no limit
+    #[serde(default)]
+    pub egress_allow: Vec<String>,
+    /// hosts or CIDRs that outbound fetch is denied to
+    #[serde(default)]
+    pub egress_deny: Vec<String>,
+}
+
+impl Spec {
+    /// from_model parses the spec of a deployment, fallback to default if invalid
+    pub fn from_model(dp: &deployment::Model) -> Self {
+        serde_json::from_value(dp.spec.clone()).unwrap_or_default()
     }
 }
 
-/// create a deployment
+/// create a deployment of project, playground_id is the playground source to build, 0 if no source.
+/// Deployment not built by waiting deployer should be created with other status than WaitDeploy,
+/// otherwise waiting deployer may pick it up before it is handled.
 pub async fn create(
-    owner_id: i32,
+    project: &project::Model,
     owner_uuid: String,
-    project_id: i32,
-    project_uuid: String,
     domain: String,
     deploy_type: DeployType,
+    playground_id: i32,
+    spec: &Spec,
+    status: Status,
 ) -> Result<deployment::Model> {
-    let spec = Spec::default();
     let now = now_time();
+    let message = match status {
+        Status::WaitDeploy => "Waiting to deploy",
+        Status::Compiling => "Compiling",
+        Status::Uploading => "Uploading",
+        Status::Deploying => "Deploying",
+        Status::Success => "Success",
+        Status::Failed => "Failed",
+    };
     let model = deployment::Model {
         id: 0,
-        owner_id,
+        owner_id: project.owner_id,
         owner_uuid,
-        project_id,
-        project_uuid,
+        project_id: project.id,
+        project_uuid: project.uuid.clone(),
         task_id: uuid::Uuid::new_v4().to_string(),
         domain,
-        spec: serde_json::to_value(&spec)?,
+        spec: serde_json::to_value(spec)?,
         deploy_type: deploy_type.to_string(),
-        deploy_status: Status::WaitDeploy.to_string(),
-        deploy_message: "Waiting to deploy".to_string(),
+        deploy_status: status.to_string(),
+        deploy_message: message.to_string(),
         status: DeploymentStatus::Active.to_string(),
         created_at: now,
         updated_at: now,
@@ -83,6 +99,7 @@ pub async fn create(
         success_count: 0,
         failed_count: 0,
         total_count: 0,
+        playground_id,
     };
     let mut active_model = model.into_active_model();
     active_model.id = Default::default();
@@ -96,6 +113,7 @@ pub async fn list_by_deploy_status(status: Status) -> Result<Vec<deployment::Mod
     let db = DB.get().unwrap();
     let models = deployment::Entity::find()
         .filter(deployment::Column::DeployStatus.contains(status.to_string()))
+        .order_by_asc(deployment::Column::Id)
         .all(db)
         .await?;
     Ok(models)
@@ -130,12 +148,32 @@ pub async fn set_rips(id: i32, rips: String, total_count: i32) -> Result<()> {
     Ok(())
 }
 
-/// success_ids returns a list of success deployment ids
+/// set_counts sets task counts of a deployment,
+/// updated_at is not changed as it is used to check stale deploy status
+pub async fn set_counts(
+    id: i32,
+    success_count: i32,
+    failed_count: i32,
+    total_count: i32,
+) -> Result<()> {
+    let db = DB.get().unwrap();
+    deployment::Entity::update_many()
+        .col_expr(deployment::Column::SuccessCount, Expr::value(success_count))
+        .col_expr(deployment::Column::FailedCount, Expr::value(failed_count))
+        .col_expr(deployment::Column::TotalCount, Expr::value(total_count))
+        .filter(deployment::Column::Id.eq(id))
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
+/// success_ids returns a list of success and active deployment ids
 pub async fn success_ids() -> Result<Vec<i32>> {
     let db = DB.get().unwrap();
     let models = deployment::Entity::find()
         .column(deployment::Column::Id)
         .filter(deployment::Column::DeployStatus.eq(Status::Success.to_string()))
+        .filter(deployment::Column::Status.eq(DeploymentStatus::Active.to_string()))
         .order_by_asc(deployment::Column::Id)
         .all(db)
         .await?;
@@ -162,3 +200,54 @@ pub async fn get_for_status(id: i32, task_id: String) -> Result<Option<deploymen
         .await?;
     Ok(model)
 }
+
+/// list_success_by_project returns success deployments of a project by status
+pub async fn list_success_by_project(
+    project_id: i32,
+    status: DeploymentStatus,
+) -> Result<Vec<deployment::Model>> {
+    let db = DB.get().unwrap();
+    let models = deployment::Entity::find()
+        .filter(deployment::Column::ProjectId.eq(project_id))
+        .filter(deployment::Column::DeployStatus.eq(Status::Success.to_string()))
+        .filter(deployment::Column::Status.eq(status.to_string()))
+        .order_by_asc(deployment::Column::Id)
+        .all(db)
+        .await?;
+    Ok(models)
+}
+
+/// set_status sets the status of a deployment
+pub async fn set_status(deploy_id: i32, status: DeploymentStatus) -> Result<()> {
+    let db = DB.get().unwrap();
+    deployment::Entity::update_many()
+        .col_expr(deployment::Column::Status, Expr::value(status.to_string()))
+        .col_expr(deployment::Column::UpdatedAt, Expr::value(now_time()))
+        .filter(deployment::Column::Id.eq(deploy_id))
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
+/// list_by_project returns latest deployments of a project, deleted deployments are not included
+pub async fn list_by_project(project_id: i32, limit: u64) -> Result<Vec<deployment::Model>> {
+    let db = DB.get().unwrap();
+    let models = deployment::Entity::find()
+        .filter(deployment::Column::ProjectId.eq(project_id))
+        .filter(deployment::Column::Status.ne(DeploymentStatus::Deleted.to_string()))
+        .order_by_desc(deployment::Column::Id)
+        .limit(limit)
+        .all(db)
+        .await?;
+    Ok(models)
+}
+
+/// get_by_id returns a deployment by id
+pub async fn get_by_id(id: i32) -> Result<Option<deployment::Model>> {
+    let db = DB.get().unwrap();
+    let model = deployment::Entity::find()
+        .filter(deployment::Column::Id.eq(id))
+        .one(db)
+        .await?;
+    Ok(model)
+}
@@ -0,0 +1,172 @@
+use crate::{models::project_envs, now_time, DB};
+use anyhow::{anyhow, Result};
+use land_common::crypto;
+use once_cell::sync::OnceCell;
+use sea_orm::{
+    sea_query::Expr, ActiveModelTrait, ColumnTrait, EntityTrait, IntoActiveModel, QueryFilter,
+    QueryOrder, QuerySelect, TransactionTrait,
+};
+use std::collections::HashMap;
+use tracing::warn;
+
+#[derive(strum::Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum Status {
+    Active,
+    Deleted,
+}
+
+/// SECRET_KEY is the key to encrypt secret env values
+static SECRET_KEY: OnceCell<[u8; 32]> = OnceCell::new();
+
+/// init_secret sets the key to encrypt secret env values.
+/// The key is never saved in database with encrypted values.
+/// If secret is empty, secret envs are disabled.
+pub fn init_secret(secret: &str) -> Result<()> {
+    if secret.is_empty() {
+        warn!(
+            "Envs secret is not set, secret envs are disabled. Set LAND_ENVS_SECRET to enable them"
+        );
+        return Ok(());
+    }
+    SECRET_KEY
+        .set(crypto::derive_key(secret))
+        .map_err(|_| anyhow!("Envs secret is already set"))?;
+    Ok(())
+}
+
+fn secret_key() -> Result<&'static [u8; 32]> {
+    SECRET_KEY
+        .get()
+        .ok_or_else(|| anyhow!("Secret envs are disabled, envs secret is not set"))
+}
+
+/// Item is the env key-value from form
+#[derive(Debug, Clone, Default)]
+pub struct Item {
+    pub key: String,
+    pub value: String,
+    pub is_secret: bool,
+}
+
+/// list_by_project lists active envs of a project, secret values are not decrypted
+pub async fn list_by_project(project_id: i32) -> Result<Vec<project_envs::Model>> {
+    let db = DB.get().unwrap();
+    let models = project_envs::Entity::find()
+        .filter(project_envs::Column::ProjectId.eq(project_id))
+        .filter(project_envs::Column::Status.eq(Status::Active.to_string()))
+        .order_by_asc(project_envs::Column::Id)
+        .all(db)
+        .await?;
+    Ok(models)
+}
+
+/// update replaces all envs of a project.
+/// If a secret item has empty value, it keeps the old secret value.
+pub async fn update(owner_id: i32, project_id: i32, items: Vec<Item>) -> Result<()> {
+    let olds = list_by_project(project_id).await?;
+    let olds: HashMap<String, project_envs::Model> =
+        olds.into_iter().map(|m| (m.env_key.clone(), m)).collect();
+
+    let db = DB.get().unwrap();
+    let now = now_time();
+    let mut models = vec![];
+    for item in items {
+        let (env_value, env_salt) = if item.is_secret {
+            if item.value.is_empty() {
+                let old = olds.get(&item.key).filter(|m| m.is_secret);
+                if old.is_none() {
+                    return Err(anyhow!("Secret value of {} is empty", item.key));
+                }
+                let old = old.unwrap();
+                (old.env_value.clone(), old.env_salt.clone())
+            } else {
+                let (nonce, cipher) = crypto::encrypt(secret_key()?, &item.value)?;
+                (cipher, nonce)
+            }
+        } else {
+            (item.value, String::new())
+        };
+        models.push(project_envs::Model {
+            id: 0,
+            owner_id,
+            project_id,
+            env_key: item.key,
+            env_value,
+            env_salt,
+            is_secret: item.is_secret,
+            status: Status::Active.to_string(),
+            created_at: now,
+            updated_at: now,
+        });
+    }
+
+    // replace envs in a transaction, old envs are kept if any insert fails
+    let txn = db.begin().await?;
+    // set old envs deleted
+    project_envs::Entity::update_many()
+        .col_expr(
+            project_envs::Column::Status,
+            Expr::value(Status::Deleted.to_string()),
+        )
+        .col_expr(project_envs::Column::UpdatedAt, Expr::value(now))
+        .filter(project_envs::Column::ProjectId.eq(project_id))
+        .filter(project_envs::Column::Status.eq(Status::Active.to_string()))
+        .exec(&txn)
+        .await?;
+
+    for model in models {
+        let mut active_model = model.into_active_model();
+        active_model.id = Default::default();
+        active_model.insert(&txn).await?;
+    }
+    txn.commit().await?;
+    Ok(())
+}
+
+/// list_values lists decrypted envs map of projects
+pub async fn list_values(project_ids: Vec<i32>) -> Result<HashMap<i32, HashMap<String, String>>> {
+    let db = DB.get().unwrap();
+    let models = project_envs::Entity::find()
+        .filter(project_envs::Column::ProjectId.is_in(project_ids))
+        .filter(project_envs::Column::Status.eq(Status::Active.to_string()))
+        .order_by_asc(project_envs::Column::Id)
+        .all(db)
+        .await?;
+    let mut map: HashMap<i32, HashMap<String, String>> = HashMap::new();
+    for model in models {
+        let value = if model.is_secret {
+            let decrypted = secret_key()
+                .and_then(|key| crypto::decrypt(key, &model.env_salt, &model.env_value));
+            match decrypted {
+                Ok(v) => v,
+                Err(e) => {
+                    warn!(
+                        project_id = model.project_id,
+                        "Decrypt env {} failed: {}", model.env_key, e
+                    );
+                    continue;
+                }
+            }
+        } else {
+            model.env_value
+        };
+        map.entry(model.project_id)
+            .or_default()
+            .insert(model.env_key, value);
+    }
+    Ok(map)
+}
+
+/// latest_updated_at returns the latest updated time of all envs, to detect envs changes
+pub async fn latest_updated_at() -> Result<Option<chrono::NaiveDateTime>> {
+    let db = DB.get().unwrap();
+    let model = project_envs::Entity::find()
+        .select_only()
+        .column(project_envs::Column::UpdatedAt)
+        .order_by_desc(project_envs::Column::UpdatedAt)
+        .into_tuple::<chrono::NaiveDateTime>()
+        .one(db)
+        .await?;
+    Ok(model)
+}
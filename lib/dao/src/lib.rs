@@ -9,10 +9,13 @@ use tracing::{debug, info, instrument};
 
 mod migration;
 
+pub mod deploy_logs;
 pub mod deploy_task;
 pub mod deploys;
+pub mod envs;
 pub mod models;
 pub mod playground;
+pub mod project_logs;
 pub mod projects;
 pub mod settings;
 pub mod store;
@@ -0,0 +1,44 @@
+use sea_orm_migration::prelude::*;
+use tracing::debug;
+
+#[derive(Iden)]
+enum Project {
+    Table,
+    CanaryDeployId,
+    CanaryWeight,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Project::Table)
+                    .add_column_if_not_exists(
+                        ColumnDef::new(Project::CanaryDeployId)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .add_column_if_not_exists(
+                        ColumnDef::new(Project::CanaryWeight)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        debug!("Migration: m09_alter_project_canary has been applied");
+        Ok(())
+    }
+
+    async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
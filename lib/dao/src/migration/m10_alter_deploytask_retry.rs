@@ -0,0 +1,37 @@
+use sea_orm_migration::prelude::*;
+use tracing::debug;
+
+#[derive(Iden)]
+enum DeployTask {
+    Table,
+    RetryCount,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(DeployTask::Table)
+                    .add_column_if_not_exists(
+                        ColumnDef::new(DeployTask::RetryCount)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        debug!("Migration: m10_alter_deploytask_retry has been applied");
+        Ok(())
+    }
+
+    async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
@@ -0,0 +1,77 @@
+use sea_orm_migration::prelude::*;
+use tracing::debug;
+
+#[derive(Iden)]
+enum DeployLog {
+    Table,
+    Id,
+    OwnerId,
+    ProjectId,
+    DeployId,
+    Content,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(DeployLog::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(DeployLog::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(DeployLog::OwnerId).integer().not_null())
+                    .col(ColumnDef::new(DeployLog::ProjectId).integer().not_null())
+                    .col(
+                        ColumnDef::new(DeployLog::DeployId)
+                            .integer()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(ColumnDef::new(DeployLog::Content).text().not_null())
+                    .col(
+                        ColumnDef::new(DeployLog::CreatedAt)
+                            .timestamp()
+                            .extra("DEFAULT CURRENT_TIMESTAMP".to_string())
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(DeployLog::UpdatedAt)
+                            .timestamp()
+                            .extra("DEFAULT CURRENT_TIMESTAMP".to_string())
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .if_not_exists()
+                    .name("idx-deploy-log-project-id")
+                    .table(DeployLog::Table)
+                    .col(DeployLog::ProjectId)
+                    .to_owned(),
+            )
+            .await?;
+
+        debug!("Migration: m11_create_deploylog_table has been applied");
+        Ok(())
+    }
+
+    async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
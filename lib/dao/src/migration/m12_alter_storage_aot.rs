@@ -0,0 +1,51 @@
+use sea_orm_migration::prelude::*;
+use tracing::debug;
+
+#[derive(Iden)]
+enum Storage {
+    Table,
+    AotTarget,
+    AotHash,
+    AotEngine,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Storage::Table)
+                    .add_column_if_not_exists(
+                        ColumnDef::new(Storage::AotTarget)
+                            .string_len(256)
+                            .not_null()
+                            .default(""),
+                    )
+                    .add_column_if_not_exists(
+                        ColumnDef::new(Storage::AotHash)
+                            .string_len(64)
+                            .not_null()
+                            .default(""),
+                    )
+                    .add_column_if_not_exists(
+                        ColumnDef::new(Storage::AotEngine)
+                            .string_len(64)
+                            .not_null()
+                            .default(""),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        debug!("Migration: m12_alter_storage_aot has been applied");
+        Ok(())
+    }
+
+    async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
@@ -0,0 +1,87 @@
+use sea_orm_migration::prelude::*;
+use tracing::debug;
+
+#[derive(Iden)]
+enum ProjectLog {
+    Table,
+    Id,
+    ProjectId,
+    DeployId,
+    ReqId,
+    Stream,
+    Message,
+    WorkerIp,
+    LoggedAt,
+    CreatedAt,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(ProjectLog::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(ProjectLog::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(ProjectLog::ProjectId).integer().not_null())
+                    .col(ColumnDef::new(ProjectLog::DeployId).integer().not_null())
+                    .col(ColumnDef::new(ProjectLog::ReqId).string_len(64).not_null())
+                    .col(ColumnDef::new(ProjectLog::Stream).string_len(12).not_null())
+                    .col(ColumnDef::new(ProjectLog::Message).text().not_null())
+                    .col(
+                        ColumnDef::new(ProjectLog::WorkerIp)
+                            .string_len(64)
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(ProjectLog::LoggedAt).timestamp().not_null())
+                    .col(
+                        ColumnDef::new(ProjectLog::CreatedAt)
+                            .timestamp()
+                            .extra("DEFAULT CURRENT_TIMESTAMP".to_string())
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .if_not_exists()
+                    .name("idx-project-log-project-id")
+                    .table(ProjectLog::Table)
+                    .col(ProjectLog::ProjectId)
+                    .col(ProjectLog::Id)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .if_not_exists()
+                    .name("idx-project-log-created-at")
+                    .table(ProjectLog::Table)
+                    .col(ProjectLog::CreatedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        debug!("Migration: m13_create_projectlog_table has been applied");
+        Ok(())
+    }
+
+    async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
@@ -0,0 +1,40 @@
+use sea_orm_migration::prelude::*;
+use tracing::debug;
+
+#[derive(Iden)]
+enum Deployment {
+    Table,
+    Spec,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // limits saved in spec were not enforced before,
+        // clear them so existing deployments keep running unlimited, limits are opt-in
+        let spec = serde_json::json!({
+            "cpu_time_limit": null,
+            "memory_limit": null,
+            "wall_time_limit": null,
+            "fetch_limit": null,
+        });
+        manager
+            .exec_stmt(
+                Query::update()
+                    .table(Deployment::Table)
+                    .value(Deployment::Spec, spec)
+                    .to_owned(),
+            )
+            .await?;
+
+        debug!("Migration: m16_update_deploys_spec has been applied");
+        Ok(())
+    }
+
+    async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
@@ -7,6 +7,15 @@ mod m04_create_deploys_table;
 mod m05_create_storage_table;
 mod m06_create_workernode_table;
 mod m07_create_deploystask_table;
+mod m08_create_projectenvs_table;
+mod m09_alter_project_canary;
+mod m10_alter_deploytask_retry;
+mod m11_create_deploylog_table;
+mod m12_alter_storage_aot;
+mod m13_create_projectlog_table;
+mod m14_alter_project_inspect;
+mod m15_alter_deploys_playground;
+mod m16_update_deploys_spec;
 
 pub struct Migrator;
 
@@ -21,6 +30,15 @@ impl MigratorTrait for Migrator {
             Box::new(m05_create_storage_table::Migration),
             Box::new(m06_create_workernode_table::Migration),
             Box::new(m07_create_deploystask_table::Migration),
+            Box::new(m08_create_projectenvs_table::Migration),
+            Box::new(m09_alter_project_canary::Migration),
+            Box::new(m10_alter_deploytask_retry::Migration),
+            Box::new(m11_create_deploylog_table::Migration),
+            Box::new(m12_alter_storage_aot::Migration),
+            Box::new(m13_create_projectlog_table::Migration),
+            Box::new(m14_alter_project_inspect::Migration),
+            Box::new(m15_alter_deploys_playground::Migration),
+            Box::new(m16_update_deploys_spec::Migration),
         ]
     }
 }
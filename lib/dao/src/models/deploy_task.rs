@@ -21,6 +21,7 @@ pub struct Model {
     pub created_at: DateTime,
     pub updated_at: DateTime,
     pub message: String,
+    pub retry_count: i32,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -27,6 +27,7 @@ pub struct Model {
     pub success_count: i32,
     pub failed_count: i32,
     pub total_count: i32,
+    pub playground_id: i32,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
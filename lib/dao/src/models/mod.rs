@@ -2,10 +2,13 @@
 
 pub mod prelude;
 
+pub mod deploy_log;
 pub mod deploy_task;
 pub mod deployment;
 pub mod playground;
 pub mod project;
+pub mod project_envs;
+pub mod project_log;
 pub mod settings;
 pub mod storage;
 pub mod user_info;
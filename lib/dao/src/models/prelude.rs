@@ -1,9 +1,12 @@
 //! `SeaORM` Entity. Generated by sea-orm-codegen 0.12.15
 
+pub use super::deploy_log::Entity as DeployLog;
 pub use super::deploy_task::Entity as DeployTask;
 pub use super::deployment::Entity as Deployment;
 pub use super::playground::Entity as Playground;
 pub use super::project::Entity as Project;
+pub use super::project_envs::Entity as ProjectEnvs;
+pub use super::project_log::Entity as ProjectLog;
 pub use super::settings::Entity as Settings;
 pub use super::storage::Entity as Storage;
 pub use super::user_info::Entity as UserInfo;
@@ -24,6 +24,9 @@ pub struct Model {
     #[sea_orm(column_type = "Text", nullable)]
     pub metadata: Option<String>,
     pub deploy_message: String,
+    pub canary_deploy_id: i32,
+    pub canary_weight: i32,
+    pub inspect_enabled: bool,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
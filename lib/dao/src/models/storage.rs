@@ -20,6 +20,9 @@ pub struct Model {
     pub created_at: DateTime,
     pub updated_at: DateTime,
     pub deleted_at: Option<DateTime>,
+    pub aot_target: String,
+    pub aot_hash: String,
+    pub aot_engine: String,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -68,6 +68,13 @@ pub async fn get_by_project(project_id: i32) -> Result<Option<playground::Model>
     Ok(p)
 }
 
+/// get_by_id gets a playground by id, including disabled old playgrounds
+pub async fn get_by_id(id: i32) -> Result<Option<playground::Model>> {
+    let db = DB.get().unwrap();
+    let p = playground::Entity::find_by_id(id).one(db).await?;
+    Ok(p)
+}
+
 async fn set_old_disabled(project_id: i32, current_playground_id: i32) -> Result<()> {
     let db = DB.get().unwrap();
     playground::Entity::update_many()
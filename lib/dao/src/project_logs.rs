@@ -0,0 +1,93 @@
+use crate::{models::project_log, now_time, DB};
+use anyhow::Result;
+use sea_orm::{ColumnTrait, EntityTrait, IntoActiveModel, QueryFilter, QueryOrder, QuerySelect};
+use serde::{Deserialize, Serialize};
+
+/// Item is a guest log line shipped by worker
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Item {
+    pub req_id: String,
+    pub project_id: i32,
+    pub deploy_id: i32,
+    pub stream: String,
+    pub message: String,
+    pub timestamp: i64, // unix timestamp in milliseconds
+}
+
+/// INSERT_CHUNK_SIZE is max rows of one insert statement,
+/// to keep bind parameters under the limit of database
+const INSERT_CHUNK_SIZE: usize = 1000;
+
+/// create_batch saves log lines shipped by a worker
+pub async fn create_batch(worker_ip: &str, items: Vec<Item>) -> Result<usize> {
+    if items.is_empty() {
+        return Ok(0);
+    }
+    let db = DB.get().unwrap();
+    let now = now_time();
+    let count = items.len();
+    let mut models = items.into_iter().map(|item| {
+        let logged_at = chrono::DateTime::from_timestamp_millis(item.timestamp)
+            .map(|t| t.naive_utc())
+            .unwrap_or(now);
+        let model = project_log::Model {
+            id: 0,
+            project_id: item.project_id,
+            deploy_id: item.deploy_id,
+            req_id: item.req_id,
+            stream: item.stream,
+            message: item.message,
+            worker_ip: worker_ip.to_string(),
+            logged_at,
+            created_at: now,
+        };
+        let mut active_model = model.into_active_model();
+        active_model.id = Default::default();
+        active_model
+    });
+    loop {
+        let chunk: Vec<_> = models.by_ref().take(INSERT_CHUNK_SIZE).collect();
+        if chunk.is_empty() {
+            break;
+        }
+        project_log::Entity::insert_many(chunk).exec(db).await?;
+    }
+    Ok(count)
+}
+
+/// list lists log lines of a project in id order,
+/// if after_id is set, it lists lines after it, otherwise the latest lines
+pub async fn list(
+    project_id: i32,
+    after_id: Option<i32>,
+    limit: u64,
+) -> Result<Vec<project_log::Model>> {
+    let db = DB.get().unwrap();
+    let select = project_log::Entity::find().filter(project_log::Column::ProjectId.eq(project_id));
+    if let Some(after_id) = after_id {
+        let models = select
+            .filter(project_log::Column::Id.gt(after_id))
+            .order_by_asc(project_log::Column::Id)
+            .limit(limit)
+            .all(db)
+            .await?;
+        return Ok(models);
+    }
+    let mut models = select
+        .order_by_desc(project_log::Column::Id)
+        .limit(limit)
+        .all(db)
+        .await?;
+    models.reverse();
+    Ok(models)
+}
+
+/// delete_before deletes log lines created before the time, returns deleted count
+pub async fn delete_before(t: chrono::NaiveDateTime) -> Result<u64> {
+    let db = DB.get().unwrap();
+    let res = project_log::Entity::delete_many()
+        .filter(project_log::Column::CreatedAt.lt(t))
+        .exec(db)
+        .await?;
+    Ok(res.rows_affected)
+}
@@ -8,7 +8,7 @@ use rand::Rng;
 use random_word::Lang;
 use sea_orm::{
     sea_query::Expr, ActiveModelTrait, ActiveValue, ColumnTrait, EntityTrait, FromQueryResult,
-    ItemsAndPagesNumber, PaginatorTrait, QueryFilter, QueryOrder, QuerySelect,
+    ItemsAndPagesNumber, PaginatorTrait, QueryFilter, QueryOrder, QuerySelect, TransactionTrait,
 };
 use std::collections::HashMap;
 use tracing::info;
@@ -17,6 +17,7 @@ use tracing::info;
 #[strum(serialize_all = "lowercase")]
 pub enum Language {
     JavaScript,
+    Wasm, // prebuilt wasm component, source language is unknown
 }
 
 #[derive(strum::Display, PartialEq)]
@@ -68,6 +69,56 @@ pub async fn random_unique_name() -> Result<String> {
     Ok(name)
 }
 
+/// ensure_dev_domain returns dev domain of a project,
+/// it generates a random preview domain if project has none.
+pub async fn ensure_dev_domain(project: &project::Model) -> Result<String> {
+    if !project.dev_domain.is_empty() {
+        return Ok(project.dev_domain.clone());
+    }
+    let db = DB.get().unwrap();
+    let mut dev_domain = random_dev_domain(&project.name);
+    loop {
+        let used = project::Entity::find()
+            .filter(
+                project::Column::DevDomain
+                    .eq(&dev_domain)
+                    .or(project::Column::Name.eq(&dev_domain)),
+            )
+            .one(db)
+            .await?;
+        if used.is_none() {
+            break;
+        }
+        dev_domain = random_dev_domain(&project.name);
+    }
+    // only set dev domain if it is still empty, concurrent callers use the first one
+    let res = project::Entity::update_many()
+        .col_expr(project::Column::DevDomain, Expr::value(&dev_domain))
+        .col_expr(project::Column::UpdatedAt, Expr::value(now_time()))
+        .filter(project::Column::Id.eq(project.id))
+        .filter(project::Column::DevDomain.eq(""))
+        .exec(db)
+        .await?;
+    if res.rows_affected > 0 {
+        return Ok(dev_domain);
+    }
+    let project = project::Entity::find_by_id(project.id)
+        .one(db)
+        .await?
+        .ok_or_else(|| anyhow!("Project not found"))?;
+    Ok(project.dev_domain)
+}
+
+/// random_dev_domain generates a dev domain with random suffix for project name
+fn random_dev_domain(name: &str) -> String {
+    let suffix: String = rand::thread_rng()
+        .sample_iter(&rand::distributions::Alphanumeric)
+        .take(6)
+        .map(|c| (c as char).to_ascii_lowercase())
+        .collect();
+    format!("{}-preview-{}", name, suffix)
+}
+
 /// create_with_playground creates a new project with a playground
 pub async fn create_with_playground(
     owner_id: i32,
@@ -90,6 +141,13 @@ pub async fn create_with_playground(
     Ok((p, py))
 }
 
+/// create_blank creates a new blank project, it deploys uploaded wasm component
+pub async fn create_blank(owner_id: i32, description: String) -> Result<project::Model> {
+    let p = create_internal(owner_id, Language::Wasm, description, CreatedBy::Blank).await?;
+    info!(owner_id = owner_id, "Create blank project: {}", p.name);
+    Ok(p)
+}
+
 /// create_internal creates a new project
 async fn create_internal(
     owner_id: i32,
@@ -116,6 +174,9 @@ async fn create_internal(
         updated_at: now,
         deleted_at: None,
         metadata: None,
+        canary_deploy_id: 0,
+        canary_weight: 0,
+        inspect_enabled: false,
     };
     if created_by == CreatedBy::Playground {
         project.created_by = CreatedBy::Playground.to_string();
@@ -189,6 +250,99 @@ pub async fn update_names(id: i32, name: &str, desc: &str) -> Result<()> {
     Ok(())
 }
 
+/// set_status_with_deploys sets project status and its success deployments from one status
+/// to another in a transaction, returns the deployments to update on workers
+pub async fn set_status_with_deploys(
+    id: i32,
+    status: Status,
+    from: deploys::DeploymentStatus,
+    to: deploys::DeploymentStatus,
+) -> Result<Vec<deployment::Model>> {
+    let db = DB.get().unwrap();
+    let now = now_time();
+    let txn = db.begin().await?;
+    let deploy_data = deployment::Entity::find()
+        .filter(deployment::Column::ProjectId.eq(id))
+        .filter(deployment::Column::DeployStatus.eq(deploys::Status::Success.to_string()))
+        .filter(deployment::Column::Status.eq(from.to_string()))
+        .order_by_asc(deployment::Column::Id)
+        .all(&txn)
+        .await?;
+    project::Entity::update_many()
+        .col_expr(project::Column::Status, Expr::value(status.to_string()))
+        .col_expr(project::Column::UpdatedAt, Expr::value(now))
+        .filter(project::Column::Id.eq(id))
+        .exec(&txn)
+        .await?;
+    let ids: Vec<i32> = deploy_data.iter().map(|dp| dp.id).collect();
+    deployment::Entity::update_many()
+        .col_expr(deployment::Column::Status, Expr::value(to.to_string()))
+        .col_expr(deployment::Column::UpdatedAt, Expr::value(now))
+        .filter(deployment::Column::Id.is_in(ids))
+        .exec(&txn)
+        .await?;
+    txn.commit().await?;
+    Ok(deploy_data)
+}
+
+/// set_canary sets canary deployment and its traffic weight in percent,
+/// zero deploy_id or weight stops canary
+pub async fn set_canary(id: i32, deploy_id: i32, weight: i32) -> Result<()> {
+    let (deploy_id, weight) = if deploy_id <= 0 || weight <= 0 {
+        (0, 0)
+    } else {
+        (deploy_id, weight.min(100))
+    };
+    let db = DB.get().unwrap();
+    project::Entity::update_many()
+        .col_expr(project::Column::CanaryDeployId, Expr::value(deploy_id))
+        .col_expr(project::Column::CanaryWeight, Expr::value(weight))
+        .col_expr(project::Column::UpdatedAt, Expr::value(now_time()))
+        .filter(project::Column::Id.eq(id))
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
+/// list_canaries lists active projects with canary deployment
+pub async fn list_canaries() -> Result<Vec<project::Model>> {
+    let db = DB.get().unwrap();
+    let projects = project::Entity::find()
+        .filter(project::Column::CanaryDeployId.gt(0))
+        .filter(project::Column::CanaryWeight.gt(0))
+        .filter(project::Column::Status.eq(Status::Active.to_string()))
+        .all(db)
+        .await?;
+    Ok(projects)
+}
+
+/// set_inspect turns on or off request inspector of a project
+pub async fn set_inspect(id: i32, enabled: bool) -> Result<()> {
+    let db = DB.get().unwrap();
+    project::Entity::update_many()
+        .col_expr(project::Column::InspectEnabled, Expr::value(enabled))
+        .col_expr(project::Column::UpdatedAt, Expr::value(now_time()))
+        .filter(project::Column::Id.eq(id))
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
+/// list_inspecting lists ids of active projects with request inspector on
+pub async fn list_inspecting() -> Result<Vec<i32>> {
+    let db = DB.get().unwrap();
+    let ids = project::Entity::find()
+        .select_only()
+        .column(project::Column::Id)
+        .filter(project::Column::InspectEnabled.eq(true))
+        .filter(project::Column::Status.eq(Status::Active.to_string()))
+        .order_by_asc(project::Column::Id)
+        .into_tuple::<i32>()
+        .all(db)
+        .await?;
+    Ok(ids)
+}
+
 /// set_deploy_status sets a deploy status to a project
 pub async fn set_deploy_status(id: i32, status: deploys::Status, msg: &str) -> Result<()> {
     let db = DB.get().unwrap();
@@ -205,8 +359,13 @@ pub async fn set_deploy_status(id: i32, status: deploys::Status, msg: &str) -> R
     Ok(())
 }
 
-/// update_source updates a project source
-pub async fn update_source(id: i32, source: String) -> Result<deployment::Model> {
+/// update_source updates a project source and creates a deployment of deploy_type.
+/// Development deployment goes to dev_domain and keeps project deploy status.
+pub async fn update_source(
+    id: i32,
+    source: String,
+    deploy_type: deploys::DeployType,
+) -> Result<deployment::Model> {
     let project = get_by_id(id).await?;
     if project.is_none() {
         return Err(anyhow!("Project not found"));
@@ -238,24 +397,31 @@ pub async fn update_source(id: i32, source: String) -> Result<deployment::Model>
     let user = user.unwrap();
 
     // create new deploy
+    let domain = match deploy_type {
+        deploys::DeployType::Production => project.prod_domain.clone(),
+        deploys::DeployType::Development => ensure_dev_domain(&project).await?,
+    };
     let dp = deploys::create(
-        project.owner_id,
+        &project,
         user.uuid,
-        id,
-        project.uuid,
-        project.prod_domain,
-        deploys::DeployType::Production,
-    )
-    .await?;
-
-    // update project status to deploying
-    set_deploy_status(
-        id,
+        domain,
+        deploy_type.clone(),
+        py.id,
+        &deploys::Spec::default(),
         deploys::Status::WaitDeploy,
-        "Waiting to deploy after playground update",
     )
     .await?;
 
+    // update project status to deploying, preview deploy does not change it
+    if deploy_type == deploys::DeployType::Production {
+        set_deploy_status(
+            id,
+            deploys::Status::WaitDeploy,
+            "Waiting to deploy after playground update",
+        )
+        .await?;
+    }
+
     info!(
         owner_id = project.owner_id,
         project_id = id,
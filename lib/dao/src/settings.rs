@@ -90,6 +90,79 @@ pub async fn set_domain_settings(domain_suffix: &str, http_protocol: &str) -> Re
     set(DOMAIN_SETTINGS_KEY, settings).await
 }
 
+#[derive(strum::Display, strum::EnumString, PartialEq)]
+#[strum(serialize_all = "lowercase")]
+pub enum SuccessPolicy {
+    All,     // all workers must deploy success
+    Quorum,  // more than half of workers deploy success
+    AtLeast, // at least min_success workers deploy success, capped by workers count
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DeploySettings {
+    pub success_policy: String,
+    pub min_success: i32,
+    pub max_retries: i32,
+    pub retry_backoff_secs: i64,
+    pub compile_timeout_secs: i64,
+    pub deploy_timeout_secs: i64,
+}
+
+impl Default for DeploySettings {
+    fn default() -> Self {
+        Self {
+            success_policy: SuccessPolicy::All.to_string(),
+            min_success: 1,
+            max_retries: 3,
+            retry_backoff_secs: 5,
+            compile_timeout_secs: 600,
+            deploy_timeout_secs: 600,
+        }
+    }
+}
+
+impl DeploySettings {
+    /// is_success checks deployment result by success policy
+    pub fn is_success(&self, success_count: i32, total_count: i32) -> bool {
+        if total_count == 0 {
+            return false;
+        }
+        let policy = self
+            .success_policy
+            .parse::<SuccessPolicy>()
+            .unwrap_or(SuccessPolicy::All);
+        match policy {
+            SuccessPolicy::All => success_count == total_count,
+            SuccessPolicy::Quorum => success_count * 2 > total_count,
+            SuccessPolicy::AtLeast => success_count >= self.min_success.clamp(1, total_count),
+        }
+    }
+
+    /// retry_backoff returns waiting seconds before next retry, doubled each retry
+    pub fn retry_backoff(&self, retry_count: i32) -> i64 {
+        self.retry_backoff_secs.max(1) << retry_count.clamp(0, 10)
+    }
+
+    /// should_retry checks whether a failed task has retries left and its backoff is passed
+    pub fn should_retry(&self, retry_count: i32, elapsed_secs: i64) -> bool {
+        retry_count < self.max_retries && elapsed_secs >= self.retry_backoff(retry_count)
+    }
+}
+
+static DEPLOY_SETTINGS_KEY: &str = "deploy-settings";
+
+/// get_deploy_settings get deploy settings, returns default if not set
+pub async fn get_deploy_settings() -> Result<DeploySettings> {
+    let settings: Option<DeploySettings> = get(DEPLOY_SETTINGS_KEY).await?;
+    Ok(settings.unwrap_or_default())
+}
+
+/// set_deploy_settings set deploy settings
+pub async fn set_deploy_settings(settings: DeploySettings) -> Result<()> {
+    set(DEPLOY_SETTINGS_KEY, settings).await
+}
+
 /// init_defaults init defaults
 pub async fn init_defaults() -> Result<()> {
     let v = get_value(DOMAIN_SETTINGS_KEY).await?;
@@ -99,3 +172,26 @@ pub async fn init_defaults() -> Result<()> {
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::DeploySettings;
+
+    #[test]
+    fn test_should_retry() {
+        let settings = DeploySettings::default();
+        // backoff is not passed
+        assert!(!settings.should_retry(0, 4));
+        assert!(settings.should_retry(0, 5));
+        assert!(settings.should_retry(2, 20));
+        // retries are used up, no matter how long it waits
+        assert!(!settings.should_retry(3, i64::MAX));
+        assert!(!settings.should_retry(10, i64::MAX));
+
+        let settings = DeploySettings {
+            max_retries: 0,
+            ..Default::default()
+        };
+        assert!(!settings.should_retry(0, i64::MAX));
+    }
+}
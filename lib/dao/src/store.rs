@@ -41,6 +41,9 @@ pub async fn create(
         created_at: now,
         updated_at: now,
         deleted_at: None,
+        aot_target: String::new(),
+        aot_hash: String::new(),
+        aot_engine: String::new(),
     };
     let mut active_model = model.into_active_model();
     active_model.id = Default::default();
@@ -67,6 +70,27 @@ pub async fn set_success(id: i32, target: Option<String>) -> Result<()> {
     Ok(())
 }
 
+/// set_aot sets precompiled aot artifact of storage,
+/// engine is the fingerprint of engine that compiles the artifact
+pub async fn set_aot(id: i32, target: &str, hash: &str, engine: &str) -> Result<()> {
+    let db = DB.get().unwrap();
+    storage::Entity::update_many()
+        .col_expr(storage::Column::AotTarget, Expr::value(target))
+        .col_expr(storage::Column::AotHash, Expr::value(hash))
+        .col_expr(storage::Column::AotEngine, Expr::value(engine))
+        .col_expr(storage::Column::UpdatedAt, Expr::value(now_time()))
+        .filter(storage::Column::Id.eq(id))
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
+/// get_success_by_deploy gets success storage by deploy id
+pub async fn get_success_by_deploy(deploy_id: i32) -> Result<Option<storage::Model>> {
+    let mut data = list_success_by_deploys(vec![deploy_id]).await?;
+    Ok(data.remove(&deploy_id))
+}
+
 /// list_success_by_deploys list success storage by deploy ids
 pub async fn list_success_by_deploys(deploy_ids: Vec<i32>) -> Result<HashMap<i32, storage::Model>> {
     let db = DB.get().unwrap();
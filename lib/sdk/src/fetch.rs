@@ -14,6 +14,9 @@ impl Default for RequestOptions {
 /// `fetch` is a helper function to make http request.
 /// It will return a `Response` or `RequestError`.
 ///
+/// The request body is streamed to the destination. A writable body is closed
+/// when the request is sent, so write all data before calling `fetch`.
+///
 /// # Example
 ///
 /// ```no_run
@@ -645,6 +645,12 @@ pub mod land {
             pub type RequestOptions = super::super::super::land::http::types::RequestOptions;
             #[allow(unused_unsafe, clippy::all)]
             /// send request function
+            ///
+            /// The request body is streamed to the destination, not read into memory.
+            /// A writable body is closed when the request is sent, so it must be fully
+            /// written before calling send-request. Streaming a body that is still
+            /// being written is not supported, because the guest is blocked until the
+            /// response is returned.
             pub fn send_request(
                 req: &Request,
                 options: RequestOptions,
@@ -30,6 +30,8 @@ impl BreadCrumb {
             ],
             BreadCrumbKey::ProjectSingle
             | BreadCrumbKey::ProjectTraffic
+            | BreadCrumbKey::ProjectLogs
+            | BreadCrumbKey::ProjectInspect
             | BreadCrumbKey::ProjectSettings => vec![BreadCrumb {
                 title: "Projects".to_string(),
                 link: Some("/projects".to_string()),
@@ -113,6 +115,8 @@ pub enum BreadCrumbKey {
     ProjectNew,
     ProjectSingle,
     ProjectTraffic,
+    ProjectLogs,
+    ProjectInspect,
     ProjectSettings,
     SignIn,
     NotFound,
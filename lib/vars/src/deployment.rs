@@ -0,0 +1,78 @@
+use anyhow::Result;
+use land_dao::{deploy_task, deploys, models::deployment};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeployWorker {
+    pub ip: String,
+    pub status: String,
+    pub message: String,
+    pub retry_count: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Deployment {
+    pub id: i32,
+    pub task_id: String,
+    pub domain: String,
+    pub deploy_type: String,
+    pub deploy_status: String,
+    pub deploy_message: String,
+    pub status: String,
+    pub created_at: i64,
+    pub updated_at: i64,
+    pub is_active: bool,
+    pub is_success: bool,
+    pub is_preview: bool,
+    pub success_count: i32,
+    pub total_count: i32,
+    pub workers: Vec<DeployWorker>,
+}
+
+impl Deployment {
+    pub fn new(m: deployment::Model) -> Self {
+        let is_success = m.deploy_status == deploys::Status::Success.to_string();
+        let is_preview = m.deploy_type == deploys::DeployType::Development.to_string();
+        Deployment {
+            id: m.id,
+            task_id: m.task_id,
+            domain: m.domain,
+            deploy_type: m.deploy_type,
+            deploy_message: m.deploy_message,
+            is_active: is_success && m.status == deploys::DeploymentStatus::Active.to_string(),
+            is_success,
+            is_preview,
+            success_count: m.success_count,
+            total_count: m.total_count,
+            workers: vec![],
+            deploy_status: m.deploy_status,
+            status: m.status,
+            created_at: m.created_at.and_utc().timestamp(),
+            updated_at: m.updated_at.and_utc().timestamp(),
+        }
+    }
+    pub fn new_from_models(models: Vec<deployment::Model>) -> Vec<Self> {
+        models.into_iter().map(Deployment::new).collect()
+    }
+    /// new_with_workers creates deployments with deploy status of each worker
+    pub async fn new_with_workers(models: Vec<deployment::Model>) -> Result<Vec<Self>> {
+        let task_ids = models.iter().map(|m| m.task_id.clone()).collect();
+        let tasks = deploy_task::list_by_task_ids(task_ids).await?;
+        let deploy_type = deploy_task::TaskType::DeployWasmToWorker.to_string();
+        let mut workers: HashMap<String, Vec<DeployWorker>> = HashMap::new();
+        for task in tasks.into_iter().filter(|t| t.task_type == deploy_type) {
+            workers.entry(task.task_id).or_default().push(DeployWorker {
+                ip: task.worker_ip,
+                status: task.status,
+                message: task.message,
+                retry_count: task.retry_count,
+            });
+        }
+        let mut deployments = Self::new_from_models(models);
+        for dp in deployments.iter_mut() {
+            dp.workers = workers.remove(&dp.task_id).unwrap_or_default();
+        }
+        Ok(deployments)
+    }
+}
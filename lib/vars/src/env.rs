@@ -0,0 +1,31 @@
+use land_dao::models::project_envs;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Env {
+    pub id: i32,
+    pub key: String,
+    pub value: String,
+    pub is_secret: bool,
+    pub updated_at: i64,
+}
+
+impl Env {
+    pub fn new(m: project_envs::Model) -> Self {
+        Env {
+            id: m.id,
+            key: m.env_key,
+            // secret value is never shown
+            value: if m.is_secret {
+                String::new()
+            } else {
+                m.env_value
+            },
+            is_secret: m.is_secret,
+            updated_at: m.updated_at.and_utc().timestamp(),
+        }
+    }
+    pub fn new_from_models(models: Vec<project_envs::Model>) -> Vec<Self> {
+        models.into_iter().map(Env::new).collect()
+    }
+}
@@ -15,6 +15,15 @@ pub use project::Project;
 mod token;
 pub use token::Token;
 
+mod deployment;
+pub use deployment::{DeployWorker, Deployment};
+
+mod env;
+pub use env::Env;
+
+mod log;
+pub use log::LogLine;
+
 mod worker;
 pub use worker::Worker;
 
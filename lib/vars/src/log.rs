@@ -0,0 +1,28 @@
+use land_dao::models::project_log;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LogLine {
+    pub id: i32,
+    pub req_id: String,
+    pub deploy_id: i32,
+    pub stream: String,
+    pub message: String,
+    pub logged_at: i64, // unix timestamp in milliseconds
+}
+
+impl LogLine {
+    pub fn new(m: project_log::Model) -> Self {
+        LogLine {
+            id: m.id,
+            req_id: m.req_id,
+            deploy_id: m.deploy_id,
+            stream: m.stream,
+            message: m.message,
+            logged_at: m.logged_at.and_utc().timestamp_millis(),
+        }
+    }
+    pub fn new_from_models(models: Vec<project_log::Model>) -> Vec<Self> {
+        models.into_iter().map(LogLine::new).collect()
+    }
+}
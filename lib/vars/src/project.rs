@@ -24,9 +24,13 @@ pub struct Project {
     pub deploy_message: String,
     pub is_deploy_success: bool,
     pub is_editable: bool,
+    pub is_uploadable: bool,
     pub source: Option<String>,
     pub owner_id: i32,
     pub owner: Option<AuthUser>,
+    pub canary_deploy_id: i32,
+    pub canary_weight: i32,
+    pub inspect_enabled: bool,
 }
 
 impl Project {
@@ -82,15 +86,23 @@ impl Project {
             deploy_message: project.deploy_message.clone(),
             is_deploy_success: project.deploy_status == deploys::Status::Success.to_string(),
             is_editable: project.created_by == CreatedBy::Playground.to_string(),
+            is_uploadable: project.created_by == CreatedBy::Blank.to_string(),
             source: None,
             owner_id: project.owner_id,
             owner: None,
+            canary_deploy_id: project.canary_deploy_id,
+            canary_weight: project.canary_weight,
+            inspect_enabled: project.inspect_enabled,
         })
     }
 
     /// new_with_source creates a new project from a model with playground source
     pub async fn new_with_source(project: &project::Model) -> anyhow::Result<Self> {
         let mut project = Project::new(project).await?;
+        // blank project deploys uploaded wasm, no source
+        if project.created_by == CreatedBy::Blank.to_string() {
+            return Ok(project);
+        }
         if project.created_by != CreatedBy::Playground.to_string() {
             return Err(anyhow!("Project is not created by playground"));
         }
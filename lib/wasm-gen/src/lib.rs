@@ -1,9 +1,11 @@
 use anyhow::{anyhow, Result};
 use std::{
     collections::HashMap,
-    io::Write,
+    io::{BufRead, BufReader, Read, Write},
     path::{Path, PathBuf},
     process::{Command, Stdio},
+    sync::Arc,
+    time::{Duration, Instant},
 };
 use tracing::debug;
 use wit_bindgen_core::{wit_parser::Resolve, Files, WorldGenerator};
@@ -78,8 +80,160 @@ fn find_cmd(cmd: &str) -> Result<PathBuf> {
     Ok(c)
 }
 
+/// BuildLogger receives build logs line by line
+pub type BuildLogger = Arc<dyn Fn(&str) + Send + Sync>;
+
+/// BuildOpts is the options of running build commands
+#[derive(Clone)]
+pub struct BuildOpts {
+    /// timeout kills the build command when the whole build runs too long
+    pub timeout: Option<Duration>,
+    /// max_output is the max bytes of stdout and stderr to keep
+    pub max_output: usize,
+    /// logger receives outputs of build commands and step timings
+    pub logger: Option<BuildLogger>,
+}
+
+impl Default for BuildOpts {
+    fn default() -> Self {
+        Self {
+            timeout: None,
+            max_output: 64 * 1024,
+            logger: None,
+        }
+    }
+}
+
+impl BuildOpts {
+    /// log writes message to logger if set
+    fn log(&self, msg: &str) {
+        if let Some(logger) = &self.logger {
+            logger(msg);
+        }
+    }
+
+    /// deadline returns the deadline of build started now, steps share the timeout
+    fn deadline(&self) -> Option<Instant> {
+        self.timeout.map(|timeout| Instant::now() + timeout)
+    }
+}
+
+/// read_lines reads all lines from reader to avoid blocking child process,
+/// each line is sent to logger once read, but only max bytes are kept and logged
+fn read_lines<R: Read>(reader: R, max: usize, logger: Option<BuildLogger>) -> String {
+    let mut reader = BufReader::new(reader);
+    let mut data = Vec::new();
+    let mut truncated = false;
+    let mut line = Vec::new();
+    // limit line size, a long line without newline is read in chunks
+    let limit = max as u64 + 1;
+    loop {
+        line.clear();
+        match reader.by_ref().take(limit).read_until(b'\n', &mut line) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {}
+        }
+        if truncated {
+            continue;
+        }
+        if data.len() + line.len() > max {
+            truncated = true;
+            if let Some(logger) = &logger {
+                logger("...(output truncated)");
+            }
+            continue;
+        }
+        if let Some(logger) = &logger {
+            logger(String::from_utf8_lossy(&line).trim_end());
+        }
+        data.extend_from_slice(&line);
+    }
+    let mut output = String::from_utf8_lossy(&data).to_string();
+    if truncated {
+        output.push_str("\n...(output truncated)");
+    }
+    output
+}
+
+/// run_command runs command with stdin data, kills it if build deadline is passed.
+/// Outputs are streamed to logger line by line. It returns stdout if success,
+/// stderr as error if failed and outputs are not logged.
+fn run_command(
+    mut cmd: Command,
+    stdin: Option<Vec<u8>>,
+    opts: &BuildOpts,
+    deadline: Option<Instant>,
+) -> Result<String> {
+    let name = cmd.get_program().to_string_lossy().to_string();
+    let mut child = cmd
+        .stdin(if stdin.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| anyhow!("Failed to execute {}: {}", name, e))?;
+    if let (Some(data), Some(mut writer)) = (stdin, child.stdin.take()) {
+        std::thread::spawn(move || {
+            if let Err(e) = writer.write_all(data.as_slice()) {
+                debug!("Write to stdin failed: {}", e);
+            }
+        });
+    }
+    let max_output = opts.max_output;
+    let logger = opts.logger.clone();
+    let stdout = child
+        .stdout
+        .take()
+        .map(|out| std::thread::spawn(move || read_lines(out, max_output, logger)));
+    let logger = opts.logger.clone();
+    let stderr = child
+        .stderr
+        .take()
+        .map(|err| std::thread::spawn(move || read_lines(err, max_output, logger)));
+
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if deadline.is_some_and(|deadline| Instant::now() > deadline) {
+            let timeout = opts.timeout.unwrap_or_default().as_secs();
+            let _ = child.kill();
+            let _ = child.wait();
+            opts.log(&format!(
+                "{} is killed, build timeout after {}s",
+                name, timeout
+            ));
+            return Err(anyhow!(
+                "Build timeout after {}s, {} is killed",
+                timeout,
+                name
+            ));
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    };
+    let stdout = stdout.and_then(|h| h.join().ok()).unwrap_or_default();
+    let stderr = stderr.and_then(|h| h.join().ok()).unwrap_or_default();
+    if !status.success() {
+        // stderr is in build log already, do not log it again with the error
+        if opts.logger.is_some() {
+            return Err(anyhow!("{} failed with {}", name, status));
+        }
+        return Err(anyhow!(stderr));
+    }
+    Ok(stdout)
+}
+
 /// compile_js compile js file to wasm module
-fn compile_js(src_path: &str, dst_path: &str, js_engine: Option<String>) -> Result<()> {
+fn compile_js(
+    src_path: &str,
+    dst_path: &str,
+    js_engine: Option<String>,
+    opts: &BuildOpts,
+    deadline: Option<Instant>,
+) -> Result<()> {
     debug!("Compile js file: {}", src_path);
     let cmd = find_cmd("wizer")?;
     let dir = std::path::Path::new(src_path).parent().unwrap();
@@ -100,46 +254,35 @@ fn compile_js(src_path: &str, dst_path: &str, js_engine: Option<String>) -> Resu
 
     // call wizer to compile js to wasm
     // wizer js_engine.wasm -o {path}.wasm --allow-wasi --inherit-stdio=true --inherit-env=true
-    let mut child = Command::new(cmd)
+    let mut command = Command::new(cmd);
+    command
         .arg(&js_engine_file)
         .arg("-o")
         .arg(dst_path)
         .arg("--allow-wasi")
         .arg("--inherit-stdio=true")
         .arg("--inherit-env=true")
-        .arg("--wasm-bulk-memory=true")
-        .stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()
-        .expect("Failed to execute wizer child process");
-    let mut stdin = child.stdin.take().expect("Failed to get stdin");
-
-    std::thread::spawn(move || {
-        stdin
-            .write_all(src_content.as_slice())
-            .expect("Failed to write to stdin");
-    });
-
-    let output = child
-        .wait_with_output()
-        .expect("Failed to wait on wizer child process");
-    if !output.status.success() {
-        let err = String::from_utf8(output.stderr)?;
-        return Err(anyhow!(err));
-    }
+        .arg("--wasm-bulk-memory=true");
+    let res = run_command(command, Some(src_content), opts, deadline);
+    let _ = std::fs::remove_file(&js_engine_file);
+    let output = res?;
     // print output
-    debug!(
-        "Wizer output: \n{}",
-        std::str::from_utf8(&output.stdout).unwrap()
-    );
+    debug!("Wizer output: \n{}", output);
     debug!("Wizer success, from {} to {}", src_path, dst_path);
-    let _ = std::fs::remove_file(&js_engine_file);
     Ok(())
 }
 
 /// optimize wasm component
 pub fn optimize(path: &str) -> Result<Option<String>> {
+    optimize_with(path, &BuildOpts::default(), None)
+}
+
+/// optimize_with optimizes wasm component with build options
+fn optimize_with(
+    path: &str,
+    opts: &BuildOpts,
+    deadline: Option<Instant>,
+) -> Result<Option<String>> {
     let cmd = match find_cmd("wasm-opt") {
         Ok(cmd) => cmd,
         Err(_err) => {
@@ -147,23 +290,14 @@ pub fn optimize(path: &str) -> Result<Option<String>> {
         }
     };
     let target = path.replace(".wasm", ".opt.wasm");
-    let child = Command::new(cmd)
+    let mut command = Command::new(cmd);
+    command
         .arg("-O3") // use O3 instead of --strip-debug, https://github.com/fastly/js-compute-runtime/commit/dd91fa506b74487b70dc5bec510e89de95e1c569
         // .arg("--strip-debug")
         .arg("-o")
         .arg(&target)
-        .arg(path)
-        .stdout(Stdio::inherit())
-        .stderr(Stdio::inherit())
-        .spawn()
-        .expect("Failed to execute wasm-opt child process");
-    let output = child
-        .wait_with_output()
-        .expect("Failed to wait on wasm-opt child process");
-    if !output.status.success() {
-        let err = String::from_utf8(output.stderr)?;
-        return Err(anyhow::anyhow!(err));
-    }
+        .arg(path);
+    run_command(command, None, opts, deadline)?;
     debug!("Wasm-opt success, from {} to {}", path, target);
     let _ = std::fs::remove_file(path);
     Ok(Some(target))
@@ -171,13 +305,27 @@ pub fn optimize(path: &str) -> Result<Option<String>> {
 
 /// componentize_wasm compile wasm to wasm component
 pub fn componentize_wasm(target: &str) -> Result<()> {
+    componentize_wasm_with(target, &BuildOpts::default(), None)
+}
+
+/// componentize_wasm_with compile wasm to wasm component with build options
+fn componentize_wasm_with(target: &str, opts: &BuildOpts, deadline: Option<Instant>) -> Result<()> {
     // use wasm-opt to optimize wasm if wasm-opt exists
-    if let Some(op) = optimize(target)? {
+    let st = Instant::now();
+    if let Some(op) = optimize_with(target, opts, deadline)? {
         std::fs::rename(op, target)?;
+        opts.log(&format!("Optimize done in {}ms", st.elapsed().as_millis()));
+    } else {
+        opts.log("Optimize skipped, wasm-opt not found");
     }
 
     // encode wasm module to component
+    let st = Instant::now();
     encode_component(target, target)?;
+    opts.log(&format!(
+        "Componentize done in {}ms",
+        st.elapsed().as_millis()
+    ));
 
     // check target exists
     if !std::path::Path::new(target).exists() {
@@ -195,12 +343,12 @@ fn encode_component(src: &str, dest: &str) -> Result<()> {
     let wasi_adapter = include_bytes!("../engine/wasi_snapshot_preview1.reactor.wasm");
     let component = ComponentEncoder::default()
         .module(&file_bytes)
-        .expect("Pull custom sections from module")
+        .map_err(|e| anyhow!("Pull custom sections from module: {}", e))?
         .validate(true)
         .adapter("wasi_snapshot_preview1", wasi_adapter)
-        .expect("Add adapter to component")
+        .map_err(|e| anyhow!("Add adapter to component: {}", e))?
         .encode()
-        .expect("Encode component");
+        .map_err(|e| anyhow!("Encode component: {}", e))?;
     let output = src.replace(".wasm", ".component.wasm");
     std::fs::write(&output, component)?;
     debug!("Convert component success, from {} to {}", src, dest);
@@ -214,7 +362,21 @@ fn encode_component(src: &str, dest: &str) -> Result<()> {
 
 /// componentize_js compile to js to wasm component
 pub fn componentize_js(src: &str, target: &str, js_engine: Option<String>) -> Result<()> {
+    componentize_js_with(src, target, js_engine, &BuildOpts::default())
+}
+
+/// componentize_js_with compile to js to wasm component with build options
+pub fn componentize_js_with(
+    src: &str,
+    target: &str,
+    js_engine: Option<String>,
+    opts: &BuildOpts,
+) -> Result<()> {
+    // all steps share one deadline, so the whole build is limited by timeout
+    let deadline = opts.deadline();
     // compile js to wizer
-    compile_js(src, target, js_engine)?;
-    componentize_wasm(target)
+    let st = Instant::now();
+    compile_js(src, target, js_engine, opts, deadline)?;
+    opts.log(&format!("Compile done in {}ms", st.elapsed().as_millis()));
+    componentize_wasm_with(target, opts, deadline)
 }
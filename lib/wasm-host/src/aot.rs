@@ -0,0 +1,176 @@
+use crate::engine::fingerprint;
+use anyhow::{anyhow, Result};
+use land_common::crypto;
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info};
+
+/// AOT_SECRET is the secret to sign aot artifact, sha256 is used if not set
+static AOT_SECRET: OnceCell<String> = OnceCell::new();
+
+/// init_secret sets the secret to sign aot artifacts by hmac-sha256,
+/// the signing key is derived from the secret with "aot" label
+pub fn init_secret(secret: &str) {
+    let _ = AOT_SECRET.set(crypto::derive_subkey(secret, "aot"));
+}
+
+/// Manifest is the sidecar file of aot artifact,
+/// it is checked before deserializing the artifact
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Manifest {
+    /// sha256 digest of source wasm file
+    pub wasm_hash: String,
+    /// fingerprint of engine that compiles the artifact
+    pub engine: String,
+    /// hmac-sha256 or sha256
+    pub algorithm: String,
+    /// signature or digest of the artifact
+    pub digest: String,
+}
+
+/// manifest_path returns manifest file path of aot file
+fn manifest_path(aot_path: &str) -> String {
+    format!("{}.json", aot_path)
+}
+
+fn aot_secret() -> Option<&'static str> {
+    AOT_SECRET.get().map(|s| s.as_str())
+}
+
+/// new_manifest creates manifest of aot artifact,
+/// it is signed by hmac-sha256 if secret is set, otherwise sha256 digest
+fn new_manifest(
+    secret: Option<&str>,
+    engine: String,
+    wasm_data: &[u8],
+    aot_data: &[u8],
+) -> Manifest {
+    let (algorithm, digest) = match secret {
+        Some(secret) => (
+            "hmac-sha256".to_string(),
+            crypto::sign_bytes(secret, aot_data),
+        ),
+        None => ("sha256".to_string(), crypto::sha256_digest(aot_data)),
+    };
+    Manifest {
+        wasm_hash: crypto::sha256_digest(wasm_data),
+        engine,
+        algorithm,
+        digest,
+    }
+}
+
+/// check checks engine, source wasm and artifact with manifest
+fn check(
+    manifest: &Manifest,
+    secret: Option<&str>,
+    engine: &str,
+    wasm_data: &[u8],
+    aot_data: &[u8],
+) -> Result<()> {
+    if manifest.engine != engine {
+        return Err(anyhow!(
+            "engine dismatch: real: {}, expect: {}",
+            engine,
+            manifest.engine
+        ));
+    }
+    if crypto::sha256_digest(wasm_data) != manifest.wasm_hash {
+        return Err(anyhow!("source wasm hash dismatch"));
+    }
+    let verified = match (manifest.algorithm.as_str(), secret) {
+        ("hmac-sha256", Some(secret)) => crypto::verify_bytes(secret, aot_data, &manifest.digest),
+        ("sha256", None) => crypto::sha256_digest(aot_data) == manifest.digest,
+        _ => false,
+    };
+    if !verified {
+        return Err(anyhow!("artifact {} dismatch", manifest.algorithm));
+    }
+    Ok(())
+}
+
+/// write_manifest writes manifest of aot artifact compiled from wasm file
+pub fn write_manifest(wasm_path: &str, aot_path: &str, aot_data: &[u8]) -> Result<()> {
+    let wasm_data = std::fs::read(wasm_path)?;
+    let manifest = new_manifest(aot_secret(), fingerprint()?, &wasm_data, aot_data);
+    let path = manifest_path(aot_path);
+    std::fs::write(&path, serde_json::to_vec(&manifest)?)?;
+    debug!("Write AOT manifest: {}", path);
+    Ok(())
+}
+
+/// read_verified reads aot artifact and checks it with manifest,
+/// it returns the verified bytes to deserialize
+pub fn read_verified(wasm_path: &str, aot_path: &str) -> Result<Vec<u8>> {
+    let path = manifest_path(aot_path);
+    let content =
+        std::fs::read(&path).map_err(|e| anyhow!("read manifest error: {}, file: {}", e, path))?;
+    let manifest: Manifest = serde_json::from_slice(&content)?;
+    let engine = fingerprint()?;
+    let wasm_data = std::fs::read(wasm_path)?;
+    let aot_data = std::fs::read(aot_path)?;
+    check(&manifest, aot_secret(), &engine, &wasm_data, &aot_data)?;
+    Ok(aot_data)
+}
+
+/// remove removes aot artifact and its manifest
+pub fn remove(aot_path: &str) -> Result<()> {
+    for file in [manifest_path(aot_path), aot_path.to_string()] {
+        if std::path::Path::new(&file).exists() {
+            std::fs::remove_file(&file)?;
+            info!("Remove file: {}", file);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ENGINE: &str = "w23-test";
+    const WASM: &[u8] = b"wasm";
+    const AOT: &[u8] = b"aot artifact";
+
+    #[test]
+    fn test_check_sha256() {
+        let manifest = new_manifest(None, ENGINE.to_string(), WASM, AOT);
+        assert!(check(&manifest, None, ENGINE, WASM, AOT).is_ok());
+        // tampered artifact
+        assert!(check(&manifest, None, ENGINE, WASM, b"aot artifacT").is_err());
+        // wrong source wasm
+        assert!(check(&manifest, None, ENGINE, b"other", AOT).is_err());
+        // wrong engine
+        assert!(check(&manifest, None, "w23-other", WASM, AOT).is_err());
+        // sha256 manifest is not accepted if secret is set
+        assert!(check(&manifest, Some("secret"), ENGINE, WASM, AOT).is_err());
+    }
+
+    #[test]
+    fn test_check_hmac() {
+        let manifest = new_manifest(Some("secret"), ENGINE.to_string(), WASM, AOT);
+        assert!(check(&manifest, Some("secret"), ENGINE, WASM, AOT).is_ok());
+        assert!(check(&manifest, Some("secret"), ENGINE, WASM, b"tampered").is_err());
+        assert!(check(&manifest, Some("other"), ENGINE, WASM, AOT).is_err());
+        // hmac manifest is not accepted if secret is not set
+        assert!(check(&manifest, None, ENGINE, WASM, AOT).is_err());
+        // tampered artifact with recomputed sha256 digest
+        let forged = Manifest {
+            algorithm: "sha256".to_string(),
+            digest: crypto::sha256_digest(b"tampered"),
+            ..new_manifest(Some("secret"), ENGINE.to_string(), WASM, AOT)
+        };
+        assert!(check(&forged, Some("secret"), ENGINE, WASM, b"tampered").is_err());
+    }
+
+    #[test]
+    fn test_read_verified_missing_manifest() {
+        let dir = tempfile::tempdir().unwrap();
+        let wasm_path = dir.path().join("a.wasm");
+        let aot_path = dir.path().join("a.wasm.aot");
+        std::fs::write(&wasm_path, WASM).unwrap();
+        std::fs::write(&aot_path, AOT).unwrap();
+        let res = read_verified(wasm_path.to_str().unwrap(), aot_path.to_str().unwrap());
+        assert!(res.is_err());
+    }
+}
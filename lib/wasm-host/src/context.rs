@@ -1,15 +1,85 @@
-use crate::hostcall::HostContext;
+use crate::{hostcall::HostContext, logs::Logs};
 use axum::body::Body;
 use bytesize::ByteSize;
-use std::collections::HashMap;
-use tracing::debug;
+use std::{collections::HashMap, time::Duration};
+use tracing::{debug, warn};
 use wasmtime::ResourceLimiter;
 use wasmtime_wasi::{ResourceTable, WasiCtx, WasiCtxBuilder, WasiView};
 
+/// Limits are the runtime limits of a guest request, read from deployment spec.
+/// None means no limit.
+#[derive(Default, Clone, Debug)]
+pub struct Limits {
+    /// Max cpu time that guest can run
+    pub cpu_time: Option<Duration>,
+    /// Max memory size in bytes
+    pub memory: Option<usize>,
+    /// Max wall time of the request
+    pub wall_time: Option<Duration>,
+    /// Max fetch requests count
+    pub fetch_count: Option<u32>,
+}
+
+impl Limits {
+    /// new creates limits from spec values,
+    /// cpu time is in milliseconds, memory is in MB, wall time is in seconds.
+    /// Zero value means no limit.
+    pub fn new(
+        cpu_ms: Option<u64>,
+        mem_mb: Option<u64>,
+        wall_secs: Option<u64>,
+        fetch: Option<u64>,
+    ) -> Self {
+        Limits {
+            cpu_time: cpu_ms.filter(|v| *v > 0).map(Duration::from_millis),
+            memory: mem_mb.filter(|v| *v > 0).map(|v| v as usize * 1024 * 1024),
+            wall_time: wall_secs.filter(|v| *v > 0).map(Duration::from_secs),
+            fetch_count: fetch.filter(|v| *v > 0).map(|v| v as u32),
+        }
+    }
+}
+
+/// LimitError is returned when guest is stopped by runtime limits
+#[derive(Debug, Clone, PartialEq)]
+pub enum LimitError {
+    CpuTimeExceeded(Duration),
+    WallTimeExceeded(Duration),
+}
+
+impl std::fmt::Display for LimitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LimitError::CpuTimeExceeded(d) => write!(f, "CPU time limit exceeded: {:?}", d),
+            LimitError::WallTimeExceeded(d) => write!(f, "Wall time limit exceeded: {:?}", d),
+        }
+    }
+}
+
+impl std::error::Error for LimitError {}
+
+/// Stats are the resources that a guest request used
+#[derive(Default, Clone, Debug)]
+pub struct Stats {
+    /// cpu time counted by epoch ticks
+    pub cpu_time: Duration,
+    /// memory high-water mark in bytes
+    pub memory: usize,
+    /// fetch requests count
+    pub fetch_count: u32,
+    /// wall time since the request started
+    pub elapsed: Duration,
+    /// time to instantiate the component, none if instantiating fails
+    pub instantiate: Option<Duration>,
+    /// time that guest handles the request after instantiating, none if instantiating fails
+    pub execute: Option<Duration>,
+}
+
 #[derive(Default)]
 pub struct Limiter {
     /// Total memory allocated so far.
     pub memory_allocated: usize,
+    /// Max memory can be allocated.
+    pub memory_limit: Option<usize>,
 }
 
 impl ResourceLimiter for Limiter {
@@ -19,6 +89,18 @@ impl ResourceLimiter for Limiter {
         desired: usize,
         _maximum: Option<usize>,
     ) -> anyhow::Result<bool> {
+        // refuse growing if over memory limit
+        if let Some(limit) = self.memory_limit {
+            if self.memory_allocated + desired - current > limit {
+                warn!(
+                    "Memory growing refused, current: {}, desired: {}, limit: {}",
+                    ByteSize(current as u64),
+                    ByteSize(desired as u64),
+                    ByteSize(limit as u64),
+                );
+                return Ok(false);
+            }
+        }
         // Track the diff in memory allocated over time. As each instance will start with 0 and
         // gradually resize, this will track the total allocations throughout the lifetime of the
         // instance.
@@ -43,6 +125,9 @@ pub struct Context {
     table: ResourceTable,
     host_ctx: HostContext,
     pub limiter: Limiter,
+    limits: Limits,
+    cpu_ticks: u64,
+    logs: Logs,
 }
 
 impl WasiView for Context {
@@ -63,8 +148,10 @@ impl Default for Context {
 impl Context {
     pub fn new(envs: Option<HashMap<String, String>>) -> Self {
         let table = ResourceTable::new();
+        // capture guest stdout and stderr as request logs
+        let logs = Logs::default();
         let mut wasi_ctx_builder = WasiCtxBuilder::new();
-        wasi_ctx_builder.inherit_stdio();
+        wasi_ctx_builder.stdout(logs.stdout()).stderr(logs.stderr());
         if let Some(envs) = envs {
             for (k, v) in envs {
                 // set env key as upper case
@@ -75,13 +162,20 @@ impl Context {
             wasi_ctx: wasi_ctx_builder.build(),
             host_ctx: HostContext::new(),
             limiter: Limiter::default(),
+            limits: Limits::default(),
+            cpu_ticks: 0,
             table,
+            logs,
         }
     }
     /// get host_ctx
     pub fn host_ctx(&mut self) -> &mut HostContext {
         &mut self.host_ctx
     }
+    /// logs returns captured guest stdout and stderr of the request
+    pub fn logs(&self) -> Logs {
+        self.logs.clone()
+    }
     /// take body
     pub fn take_body(&mut self, handle: u32) -> Option<Body> {
         self.host_ctx.take_body(handle)
@@ -94,4 +188,40 @@ impl Context {
     pub fn elapsed(&self) -> tokio::time::Duration {
         self.host_ctx.elapsed()
     }
+    /// set_limits sets runtime limits for this request
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limiter.memory_limit = limits.memory;
+        self.host_ctx.set_fetch_limit(limits.fetch_count);
+        self.limits = limits;
+    }
+    /// limits returns runtime limits
+    pub fn limits(&self) -> &Limits {
+        &self.limits
+    }
+    /// add_cpu_tick adds one epoch tick that guest runs, returns error if over cpu time limit
+    pub fn add_cpu_tick(&mut self) -> Result<(), LimitError> {
+        self.cpu_ticks += 1;
+        let cpu_time = self.cpu_time();
+        if let Some(limit) = self.limits.cpu_time {
+            if cpu_time > limit {
+                return Err(LimitError::CpuTimeExceeded(limit));
+            }
+        }
+        Ok(())
+    }
+    /// stats returns the resources that the request used
+    pub fn stats(&self) -> Stats {
+        Stats {
+            cpu_time: self.cpu_time(),
+            // memory never shrinks, allocated memory is the high-water mark
+            memory: self.limiter.memory_allocated,
+            fetch_count: self.host_ctx.fetch_count(),
+            elapsed: self.elapsed(),
+            ..Default::default()
+        }
+    }
+    /// cpu_time returns the cpu time that guest runs, counted by epoch ticks
+    pub fn cpu_time(&self) -> Duration {
+        Duration::from_millis(self.cpu_ticks * crate::engine::EPOCH_INC_INTERVAL)
+    }
 }
@@ -0,0 +1,371 @@
+use anyhow::{anyhow, Result};
+use ipnet::IpNet;
+use lazy_static::lazy_static;
+use once_cell::sync::OnceCell;
+use reqwest::Url;
+use std::{
+    collections::HashMap,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    str::FromStr,
+    sync::RwLock,
+};
+
+/// Rule is a host or CIDR rule of egress policy
+#[derive(Debug, Clone)]
+enum Rule {
+    // exact host, or subdomains if starts with "*."
+    Host(String),
+    Cidr(IpNet),
+}
+
+impl Rule {
+    fn parse(s: &str) -> Result<Self> {
+        let s = s.trim().to_lowercase();
+        if s.is_empty() {
+            return Err(anyhow!("Empty egress rule"));
+        }
+        if let Ok(net) = IpNet::from_str(&s) {
+            return Ok(Rule::Cidr(net));
+        }
+        if let Ok(ip) = IpAddr::from_str(&s) {
+            return Ok(Rule::Cidr(IpNet::from(ip)));
+        }
+        let host = s.trim_start_matches("*.");
+        if host.is_empty() || host.contains(['/', ':', ' ']) {
+            return Err(anyhow!("Invalid egress rule: {}", s));
+        }
+        Ok(Rule::Host(s))
+    }
+
+    fn match_host(&self, host: &str) -> bool {
+        match self {
+            Rule::Host(rule) => {
+                if let Some(suffix) = rule.strip_prefix("*.") {
+                    host.ends_with(&format!(".{}", suffix))
+                } else {
+                    rule == host
+                }
+            }
+            Rule::Cidr(_) => false,
+        }
+    }
+
+    fn match_ip(&self, ip: &IpAddr) -> bool {
+        match self {
+            Rule::Cidr(net) => net.contains(ip),
+            Rule::Host(_) => false,
+        }
+    }
+}
+
+/// Policy is the egress policy of outbound fetch requests.
+/// Deny rules always win. If allow rules are set, destination must match one of them.
+/// Private, loopback and link-local addresses are denied unless allow_private is set.
+#[derive(Debug, Clone, Default)]
+pub struct Policy {
+    allow: Vec<Rule>,
+    deny: Vec<Rule>,
+    allow_private: bool,
+}
+
+impl Policy {
+    /// new creates policy from allow and deny rules, each rule is a host, "*.domain" or CIDR
+    pub fn new(allow: &[String], deny: &[String], allow_private: bool) -> Result<Self> {
+        let allow = allow
+            .iter()
+            .filter(|s| !s.trim().is_empty())
+            .map(|s| Rule::parse(s))
+            .collect::<Result<Vec<_>>>()?;
+        let deny = deny
+            .iter()
+            .filter(|s| !s.trim().is_empty())
+            .map(|s| Rule::parse(s))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self {
+            allow,
+            deny,
+            allow_private,
+        })
+    }
+
+    /// check_host checks host name with deny and allow host rules.
+    /// It returns Ok(true) if host is allowed explicitly by allow rules.
+    fn check_host(&self, host: &str) -> Result<bool, EgressError> {
+        if self.deny.iter().any(|r| r.match_host(host)) {
+            return Err(EgressError::NotAllowed(host.to_string()));
+        }
+        Ok(self.allow.iter().any(|r| r.match_host(host)))
+    }
+
+    /// check_ip checks resolved ip with deny and allow cidr rules
+    fn check_ip(&self, host_allowed: bool, ip: &IpAddr) -> Result<(), EgressError> {
+        if self.deny.iter().any(|r| r.match_ip(ip)) {
+            return Err(EgressError::NotAllowed(ip.to_string()));
+        }
+        if host_allowed || self.allow.is_empty() {
+            return Ok(());
+        }
+        if self.allow.iter().any(|r| r.match_ip(ip)) {
+            return Ok(());
+        }
+        Err(EgressError::NotAllowed(ip.to_string()))
+    }
+
+    /// check checks host and all resolved ips
+    fn check(&self, host: &str, ips: &[IpAddr]) -> Result<(), EgressError> {
+        let host_allowed = self.check_host(host)?;
+        for ip in ips {
+            self.check_ip(host_allowed, ip)?;
+        }
+        Ok(())
+    }
+
+    /// check_private denies private ips, unless allow_private or ip is in allow cidr rules
+    fn check_private(&self, ip: &IpAddr) -> Result<(), EgressError> {
+        if self.allow_private || !is_private(ip) {
+            return Ok(());
+        }
+        if self.allow.iter().any(|r| r.match_ip(ip)) {
+            return Ok(());
+        }
+        Err(EgressError::NotAllowed(ip.to_string()))
+    }
+}
+
+/// EgressError is the error of egress policy checking
+#[derive(Debug)]
+pub enum EgressError {
+    InvalidUrl,
+    NotAllowed(String),
+    Resolve(String),
+}
+
+impl std::fmt::Display for EgressError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EgressError::InvalidUrl => write!(f, "invalid url"),
+            EgressError::NotAllowed(dest) => write!(f, "destination not allowed: {}", dest),
+            EgressError::Resolve(msg) => write!(f, "resolve failed: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for EgressError {}
+
+/// PLATFORM_POLICY is the egress policy of the whole platform
+static PLATFORM_POLICY: OnceCell<Policy> = OnceCell::new();
+
+lazy_static! {
+    /// DEPLOY_POLICIES is the egress policy of each deployment, key is deployment id
+    static ref DEPLOY_POLICIES: RwLock<HashMap<String, Policy>> = RwLock::new(HashMap::new());
+}
+
+/// init_policy sets the platform egress policy
+pub fn init_policy(policy: Policy) {
+    let _ = PLATFORM_POLICY.set(policy);
+}
+
+fn platform_policy() -> &'static Policy {
+    PLATFORM_POLICY.get_or_init(Policy::default)
+}
+
+/// set_deploy_policies replaces all deployment egress policies
+pub fn set_deploy_policies(policies: HashMap<String, Policy>) {
+    let mut map = DEPLOY_POLICIES.write().unwrap();
+    *map = policies;
+}
+
+/// check_ips checks resolved ips with platform and deployment policies,
+/// used to check dns results when connecting, including redirected hosts
+pub fn check_ips(deploy_id: &str, host: &str, ips: &[IpAddr]) -> Result<(), EgressError> {
+    let policy = platform_policy();
+    for ip in ips {
+        policy.check_private(ip)?;
+    }
+    policy.check(host, ips)?;
+    let policies = DEPLOY_POLICIES.read().unwrap();
+    if let Some(policy) = policies.get(deploy_id) {
+        policy.check(host, ips)?;
+    }
+    Ok(())
+}
+
+/// check_url_host checks url host with platform and deployment policies without dns resolving,
+/// used to check redirect urls. Resolved ips of the host are checked by check_ips when connecting.
+pub fn check_url_host(deploy_id: &str, url: &Url) -> Result<(), EgressError> {
+    let host = url_host(url)?;
+    if let Ok(ip) = IpAddr::from_str(&host) {
+        return check_ips(deploy_id, &host, &[ip]);
+    }
+    platform_policy().check_host(&host)?;
+    let policies = DEPLOY_POLICIES.read().unwrap();
+    if let Some(policy) = policies.get(deploy_id) {
+        policy.check_host(&host)?;
+    }
+    Ok(())
+}
+
+/// check_url checks url with platform and deployment policies, it resolves the host to check ips
+pub async fn check_url(deploy_id: &str, url: &Url) -> Result<(), EgressError> {
+    let host = url_host(url)?;
+    let ips = match IpAddr::from_str(&host) {
+        Ok(ip) => vec![ip],
+        Err(_) => {
+            let port = url.port_or_known_default().unwrap_or(80);
+            tokio::net::lookup_host((host.as_str(), port))
+                .await
+                .map_err(|e| EgressError::Resolve(e.to_string()))?
+                .map(|addr| addr.ip())
+                .collect()
+        }
+    };
+    check_ips(deploy_id, &host, &ips)
+}
+
+fn url_host(url: &Url) -> Result<String, EgressError> {
+    if url.scheme() != "http" && url.scheme() != "https" {
+        return Err(EgressError::InvalidUrl);
+    }
+    let host = url.host_str().ok_or(EgressError::InvalidUrl)?;
+    // ipv6 host is in brackets
+    Ok(host
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .to_lowercase())
+}
+
+/// is_private checks if ip is loopback, private, link-local or other non-public address
+fn is_private(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_private_v4(ip),
+        IpAddr::V6(ip) => {
+            if let Some(v4) = ip.to_ipv4_mapped() {
+                return is_private_v4(&v4);
+            }
+            is_private_v6(ip)
+        }
+    }
+}
+
+fn is_private_v4(ip: &Ipv4Addr) -> bool {
+    let octets = ip.octets();
+    ip.is_private()
+        || ip.is_loopback()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_multicast()
+        || ip.is_documentation()
+        || octets[0] == 0 // 0.0.0.0/8
+        || (octets[0] == 100 && (octets[1] & 0xc0) == 64) // 100.64.0.0/10, shared address space
+        || (octets[0] == 198 && (octets[1] & 0xfe) == 18) // 198.18.0.0/15, benchmarking
+        || octets[0] >= 240 // 240.0.0.0/4, reserved
+}
+
+fn is_private_v6(ip: &Ipv6Addr) -> bool {
+    let segments = ip.segments();
+    // ::/96, ipv4-compatible address, deprecated but routed to embedded ipv4 by some stacks
+    let is_v4_compatible = segments[..6].iter().all(|s| *s == 0);
+    ip.is_loopback()
+        || ip.is_unspecified()
+        || ip.is_multicast()
+        || (segments[0] & 0xfe00) == 0xfc00 // fc00::/7, unique local
+        || (segments[0] & 0xffc0) == 0xfe80 // fe80::/10, link local
+        || is_v4_compatible
+        || (segments[0] == 0x64 && segments[1] == 0xff9b && segments[2..6] == [0; 4]) // 64:ff9b::/96, nat64
+        || (segments[0] == 0x64 && segments[1] == 0xff9b && segments[2] == 1) // 64:ff9b:1::/48, local-use nat64
+        || (segments[0] == 0x2001 && segments[1] == 0x0db8) // 2001:db8::/32, documentation
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_private_denied() {
+        let policy = Policy::default();
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "169.254.169.254",
+            "::1",
+            "fd00::1",
+            "::ffff:10.0.0.1",
+            "::10.0.0.1",
+            "::8.8.8.8",
+            "64:ff9b::a9fe:a9fe",
+            "64:ff9b:1::a00:1",
+        ] {
+            let ip = IpAddr::from_str(ip).unwrap();
+            assert!(
+                policy.check_private(&ip).is_err(),
+                "{} should be denied",
+                ip
+            );
+        }
+        let ip = IpAddr::from_str("1.1.1.1").unwrap();
+        assert!(policy.check_private(&ip).is_ok());
+    }
+
+    #[test]
+    fn test_rules() {
+        let allow = vec!["*.example.com".to_string(), "1.1.1.0/24".to_string()];
+        let deny = vec!["bad.example.com".to_string()];
+        let policy = Policy::new(&allow, &deny, false).unwrap();
+        let ip = IpAddr::from_str("8.8.8.8").unwrap();
+        assert!(policy.check("api.example.com", &[ip]).is_ok());
+        assert!(policy.check("bad.example.com", &[ip]).is_err());
+        assert!(policy.check("example.org", &[ip]).is_err());
+        let ip = IpAddr::from_str("1.1.1.1").unwrap();
+        assert!(policy.check("example.org", &[ip]).is_ok());
+    }
+
+    #[test]
+    fn test_deploy_policy_redirect() {
+        let deny = vec![
+            "internal.example.com".to_string(),
+            "203.0.113.0/24".to_string(),
+        ];
+        let policy = Policy::new(&[], &deny, false).unwrap();
+        // insert instead of set_deploy_policies, tests run in parallel
+        DEPLOY_POLICIES
+            .write()
+            .unwrap()
+            .insert("redirect".to_string(), policy);
+        // redirect to denied host or cidr of deployment is rejected
+        let url = Url::parse("https://internal.example.com/admin").unwrap();
+        assert!(check_url_host("redirect", &url).is_err());
+        let url = Url::parse("http://203.0.113.9/").unwrap();
+        assert!(check_url_host("redirect", &url).is_err());
+        let url = Url::parse("https://public.example.com/").unwrap();
+        assert!(check_url_host("redirect", &url).is_ok());
+        // other deployments are not affected
+        let url = Url::parse("https://internal.example.com/admin").unwrap();
+        assert!(check_url_host("other", &url).is_ok());
+        // redirect to private address is rejected by platform policy
+        let url = Url::parse("http://[::ffff:127.0.0.1]/").unwrap();
+        assert!(check_url_host("redirect", &url).is_err());
+    }
+
+    #[test]
+    fn test_deploy_policy_rebind() {
+        let allow = vec!["api.example.com".to_string(), "198.51.100.0/24".to_string()];
+        let deny = vec!["203.0.113.0/24".to_string()];
+        let policy = Policy::new(&allow, &deny, false).unwrap();
+        // insert instead of set_deploy_policies, tests run in parallel
+        DEPLOY_POLICIES
+            .write()
+            .unwrap()
+            .insert("rebind".to_string(), policy);
+        // host resolves to denied cidr of deployment when connecting
+        let ip = IpAddr::from_str("203.0.113.9").unwrap();
+        assert!(check_ips("rebind", "api.example.com", &[ip]).is_err());
+        // host not in allow rules resolves to ip out of allow cidr
+        let ip = IpAddr::from_str("8.8.8.8").unwrap();
+        assert!(check_ips("rebind", "evil.example.org", &[ip]).is_err());
+        assert!(check_ips("rebind", "api.example.com", &[ip]).is_ok());
+        // host rebinds to private address
+        let ip = IpAddr::from_str("10.0.0.1").unwrap();
+        assert!(check_ips("rebind", "api.example.com", &[ip]).is_err());
+    }
+}
@@ -1,5 +1,8 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use land_common::crypto;
 use lazy_static::lazy_static;
+use metrics::gauge;
+use once_cell::sync::OnceCell;
 use std::collections::HashMap;
 use std::sync::Mutex;
 use tracing::{debug, info};
@@ -17,16 +20,85 @@ pub const MODULE_VERSION: &str = "w23";
 // 10 ms to trigger epoch increment
 pub const EPOCH_INC_INTERVAL: u64 = 10;
 
-/// init_engines initialize default engine
-pub fn init_engines() -> Result<()> {
+/// EngineOpts are the settings of engine and its instance allocator
+#[derive(Debug, Clone, PartialEq)]
+pub struct EngineOpts {
+    /// use pooling allocator, or on-demand allocator if false
+    pub pooling: bool,
+    /// max count of concurrent component instances in pool
+    pub total_instances: u32,
+    /// max count of core instances in pool,
+    /// a component instance contains several core instances, such as adapters and shims
+    pub total_core_instances: u32,
+    /// max count of memories in pool
+    pub total_memories: u32,
+    /// max linear memory size of an instance in MB
+    pub max_memory_mb: usize,
+    /// max elements of a table
+    pub table_elements: u32,
+    /// max unused slots to keep warm in pool
+    pub max_unused_warm_slots: u32,
+}
+
+impl Default for EngineOpts {
+    fn default() -> Self {
+        Self {
+            pooling: true,
+            total_instances: 100,
+            total_core_instances: 400,
+            total_memories: 100,
+            max_memory_mb: 128,
+            table_elements: 98765,
+            max_unused_warm_slots: 10,
+        }
+    }
+}
+
+static ENGINE_OPTS: OnceCell<EngineOpts> = OnceCell::new();
+
+/// FINGERPRINT is the fingerprint of default engine, it is computed once
+static FINGERPRINT: OnceCell<String> = OnceCell::new();
+
+/// engine_opts returns the settings of engines, default if not initialized
+pub fn engine_opts() -> &'static EngineOpts {
+    ENGINE_OPTS.get_or_init(EngineOpts::default)
+}
+
+/// init_engines initialize default engine with settings,
+/// it fails if engine settings are already used before initializing
+pub fn init_engines(opts: EngineOpts) -> Result<()> {
+    ENGINE_OPTS
+        .set(opts)
+        .map_err(|_| anyhow!("Engine settings are already initialized"))?;
+    let opts = engine_opts();
     // try use std to run this loop. not tokio
     std::thread::spawn(|| {
         epoch_deadline_callback();
     });
-    let _ = get("default")?;
+    let fingerprint = fingerprint()?;
+    info!("Engine settings: {:?}, fingerprint: {}", opts, fingerprint);
+    report_metrics(opts, &fingerprint);
     Ok(())
 }
 
+/// report_metrics reports effective engine settings as gauges
+fn report_metrics(opts: &EngineOpts, fingerprint: &str) {
+    let allocator = if opts.pooling { "pooling" } else { "ondemand" };
+    gauge!("engine_info", "allocator" => allocator, "fingerprint" => fingerprint.to_string())
+        .set(1.0);
+    let values = [
+        ("total_instances", opts.total_instances as f64),
+        ("total_core_instances", opts.total_core_instances as f64),
+        ("total_memories", opts.total_memories as f64),
+        ("max_memory_mb", opts.max_memory_mb as f64),
+        ("table_elements", opts.table_elements as f64),
+        ("max_unused_warm_slots", opts.max_unused_warm_slots as f64),
+    ];
+    for (key, value) in values {
+        gauge!("engine_config", "key" => key).set(value);
+    }
+}
+
 /// epoch_deadline_callback
 fn epoch_deadline_callback() {
     loop {
@@ -46,6 +118,19 @@ fn epoch_deadline_callback() {
     }
 }
 
+/// fingerprint returns the fingerprint of default engine, it is used in aot file names and manifests.
+/// It contains module version and wasmtime config that affects compiled code,
+/// aot artifact can only be loaded by engine with same fingerprint
+pub fn fingerprint() -> Result<String> {
+    FINGERPRINT
+        .get_or_try_init(|| {
+            let engine = get("default")?;
+            let hash = crypto::stable_hash(&engine.precompile_compatibility_hash());
+            Ok(format!("{}-{}", MODULE_VERSION, &hash[..16]))
+        })
+        .cloned()
+}
+
 /// get engine by key
 pub fn get(key: &str) -> Result<Engine> {
     let mut map = ENGINE_MAP.lock().unwrap();
@@ -53,21 +138,28 @@ pub fn get(key: &str) -> Result<Engine> {
         return Ok(map.get(key).unwrap().clone());
     }
     info!("Create new engine for key: {}", key);
-    let config = create_config()?;
+    let config = create_config(engine_opts())?;
     let engine = Engine::new(&config).unwrap();
     map.insert(key.to_string(), engine.clone());
     Ok(engine)
 }
 
-fn create_config() -> Result<Config> {
+fn create_config(opts: &EngineOpts) -> Result<Config> {
     let mut config = Config::new();
     config.wasm_component_model(true);
     config.async_support(true);
     config.epoch_interruption(true);
+    // deserializing aot artifact of other module version fails
     config.module_version(wasmtime::ModuleVersionStrategy::Custom(
         MODULE_VERSION.to_string(),
     ))?;
 
+    if !opts.pooling {
+        config.allocation_strategy(InstanceAllocationStrategy::OnDemand);
+        debug!("Create new config: {:?}", config);
+        return Ok(config);
+    }
+
     const MB: usize = 1 << 20;
     let mut pooling_allocation_config = PoolingAllocationConfig::default();
 
@@ -75,11 +167,11 @@ fn create_config() -> Result<Config> {
     pooling_allocation_config.max_core_instance_size(MB);
 
     // Core wasm programs have 1 memory
-    pooling_allocation_config.total_memories(100);
+    pooling_allocation_config.total_memories(opts.total_memories);
     pooling_allocation_config.max_memories_per_module(1);
 
-    // allow for up to 128MiB of linear memory. Wasm pages are 64k
-    pooling_allocation_config.max_memory_size(128 * MB);
+    // allow for up to max_memory_mb of linear memory. Wasm pages are 64k
+    pooling_allocation_config.max_memory_size(opts.max_memory_mb * MB);
 
     // Core wasm programs have 1 table
     pooling_allocation_config.max_tables_per_module(1);
@@ -87,18 +179,23 @@ fn create_config() -> Result<Config> {
     // Some applications create a large number of functions, in particular
     // when compiled in debug mode or applications written in swift. Every
     // function can end up in the table
-    pooling_allocation_config.table_elements(98765);
+    pooling_allocation_config.table_elements(opts.table_elements);
 
     // Maximum number of slots in the pooling allocator to keep "warm", or those
     // to keep around to possibly satisfy an affine allocation request or an
     // instantiation of a module previously instantiated within the pool.
-    pooling_allocation_config.max_unused_warm_slots(10);
+    pooling_allocation_config.max_unused_warm_slots(opts.max_unused_warm_slots);
 
     // Use a large pool, but one smaller than the default of 1000 to avoid runnign out of virtual
     // memory space if multiple engines are spun up in a single process. We'll likely want to move
     // to the on-demand allocator eventually for most purposes; see
     // https://github.com/fastly/Viceroy/issues/255
-    pooling_allocation_config.total_core_instances(100);
+    pooling_allocation_config.total_core_instances(opts.total_core_instances);
+    // each core instance may have a table
+    pooling_allocation_config.total_tables(opts.total_core_instances);
+    // each component instance handles a request with an async stack
+    pooling_allocation_config.total_component_instances(opts.total_instances);
+    pooling_allocation_config.total_stacks(opts.total_instances);
 
     config.allocation_strategy(InstanceAllocationStrategy::Pooling(
         pooling_allocation_config,
@@ -0,0 +1,21 @@
+use lazy_static::lazy_static;
+use std::{collections::HashMap, sync::RwLock};
+
+type EnvsMap = HashMap<String, String>;
+
+lazy_static! {
+    /// ENVS is the envs of each project, key is project id
+    static ref ENVS: RwLock<HashMap<String, EnvsMap>> = RwLock::new(HashMap::new());
+}
+
+/// set_all replaces envs of all projects
+pub fn set_all(envs: HashMap<String, EnvsMap>) {
+    let mut map = ENVS.write().unwrap();
+    *map = envs;
+}
+
+/// get returns envs of a project
+pub fn get(project_id: &str) -> Option<EnvsMap> {
+    let map = ENVS.read().unwrap();
+    map.get(project_id).cloned()
+}
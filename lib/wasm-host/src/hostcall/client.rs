@@ -1,49 +1,119 @@
-use once_cell::sync::OnceCell;
-use reqwest::Client;
-use std::sync::Once;
+use crate::egress;
+use lazy_static::lazy_static;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::{redirect, Client};
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
+use std::sync::{Arc, RwLock};
 
 use super::host::land::http::types::RedirectPolicy;
 
-pub static CLIENT_INIT_ONCE: Once = Once::new();
-pub static REDIRECT_FOLLOW_POOL: OnceCell<Client> = OnceCell::new();
-pub static REDIRECT_ERROR_POOL: OnceCell<Client> = OnceCell::new();
-pub static REDIRECT_MANUAL_POOL: OnceCell<Client> = OnceCell::new();
-
-/// init_clients is used to init http clients
-pub fn init_clients() {
-    CLIENT_INIT_ONCE.call_once(|| {
-        REDIRECT_ERROR_POOL
-            .set(
-                reqwest::Client::builder()
-                    .redirect(RedirectPolicy::Error.try_into().unwrap())
-                    .build()
-                    .unwrap(),
-            )
-            .unwrap();
-        REDIRECT_FOLLOW_POOL
-            .set(
-                reqwest::Client::builder()
-                    .redirect(RedirectPolicy::Follow.try_into().unwrap())
-                    .build()
-                    .unwrap(),
-            )
-            .unwrap();
-        REDIRECT_MANUAL_POOL
-            .set(
-                reqwest::Client::builder()
-                    .redirect(RedirectPolicy::Manual.try_into().unwrap())
-                    .build()
-                    .unwrap(),
-            )
-            .unwrap();
-    });
-}
-
-/// get_client is used to get http client by redirect policy
-pub fn get_client(r: RedirectPolicy) -> Client {
+/// EgressResolver resolves dns and re-checks the results by platform and deployment egress policies,
+/// to prevent dns rebinding after request url is checked
+struct EgressResolver {
+    deploy_id: String,
+}
+
+impl Resolve for EgressResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let deploy_id = self.deploy_id.clone();
+        Box::pin(async move {
+            let host = name.as_str().to_string();
+            let addrs: Vec<SocketAddr> =
+                tokio::net::lookup_host((host.as_str(), 0)).await?.collect();
+            let ips: Vec<_> = addrs.iter().map(|addr| addr.ip()).collect();
+            egress::check_ips(&deploy_id, &host, &ips)?;
+            let addrs: Addrs = Box::new(addrs.into_iter());
+            Ok(addrs)
+        })
+    }
+}
+
+/// redirect_policy converts redirect policy to reqwest policy,
+/// followed redirects are checked by platform and deployment egress policies
+fn redirect_policy(deploy_id: &str, r: RedirectPolicy) -> redirect::Policy {
     match r {
-        RedirectPolicy::Follow => REDIRECT_FOLLOW_POOL.get().unwrap().clone(),
-        RedirectPolicy::Error => REDIRECT_ERROR_POOL.get().unwrap().clone(),
-        RedirectPolicy::Manual => REDIRECT_MANUAL_POOL.get().unwrap().clone(),
+        RedirectPolicy::Follow => {
+            let deploy_id = deploy_id.to_string();
+            redirect::Policy::custom(move |attempt| {
+                // check redirect destination by egress policy
+                if let Err(e) = egress::check_url_host(&deploy_id, attempt.url()) {
+                    return attempt.error(e);
+                }
+                // same as default policy, follow 10 redirects at most
+                if attempt.previous().len() >= 10 {
+                    return attempt.error(anyhow::anyhow!("too many redirects"));
+                }
+                attempt.follow()
+            })
+        }
+        RedirectPolicy::Error => redirect::Policy::custom(|attempt| {
+            attempt.error(anyhow::anyhow!("redirect policy is error"))
+        }),
+        RedirectPolicy::Manual => redirect::Policy::none(),
     }
-}
\ No newline at end of file
+}
+
+/// Clients are http clients of a deployment by redirect policy
+#[derive(Clone)]
+struct Clients {
+    follow: Client,
+    error: Client,
+    manual: Client,
+}
+
+impl Clients {
+    fn new(deploy_id: &str) -> reqwest::Result<Self> {
+        let build = |r: RedirectPolicy| {
+            Client::builder()
+                .dns_resolver(Arc::new(EgressResolver {
+                    deploy_id: deploy_id.to_string(),
+                }))
+                .redirect(redirect_policy(deploy_id, r))
+                .build()
+        };
+        Ok(Self {
+            follow: build(RedirectPolicy::Follow)?,
+            error: build(RedirectPolicy::Error)?,
+            manual: build(RedirectPolicy::Manual)?,
+        })
+    }
+}
+
+lazy_static! {
+    /// CLIENTS is http clients of each deployment, key is deployment id.
+    /// Clients are not shared between deployments because dns resolver and redirect policy
+    /// check the egress policy of the deployment.
+    static ref CLIENTS: RwLock<HashMap<String, Clients>> = RwLock::new(HashMap::new());
+}
+
+/// get_client is used to get http client of deployment by redirect policy
+pub fn get_client(deploy_id: &str, r: RedirectPolicy) -> reqwest::Result<Client> {
+    let clients = CLIENTS.read().unwrap().get(deploy_id).cloned();
+    let clients = match clients {
+        Some(clients) => clients,
+        None => {
+            let clients = Clients::new(deploy_id)?;
+            CLIENTS
+                .write()
+                .unwrap()
+                .entry(deploy_id.to_string())
+                .or_insert(clients)
+                .clone()
+        }
+    };
+    Ok(match r {
+        RedirectPolicy::Follow => clients.follow,
+        RedirectPolicy::Error => clients.error,
+        RedirectPolicy::Manual => clients.manual,
+    })
+}
+
+/// retain_clients keeps http clients of given deployments and drops others,
+/// it is called when deployments are synced, so clients of removed deployments are released
+pub fn retain_clients(deploy_ids: &HashSet<String>) {
+    CLIENTS
+        .write()
+        .unwrap()
+        .retain(|deploy_id, _| deploy_ids.contains(deploy_id));
+}
@@ -18,6 +18,13 @@ pub struct HostContext {
     body_sender_map: HashMap<u32, Sender>,
     body_sender_closed: HashMap<u32, bool>,
 
+    // fetch counter and limit
+    fetch_count: u32,
+    fetch_limit: Option<u32>,
+
+    // deployment id to check egress policy
+    deploy_id: String,
+
     // elapsed time need
     created_at: tokio::time::Instant,
 }
@@ -32,6 +39,9 @@ impl HostContext {
             body_stream_map: HashMap::new(),
             body_sender_map: HashMap::new(),
             body_sender_closed: HashMap::new(),
+            fetch_count: 0,
+            fetch_limit: None,
+            deploy_id: String::new(),
             created_at: tokio::time::Instant::now(),
         }
     }
@@ -59,6 +69,22 @@ impl HostContext {
         self.body_map.remove(&id)
     }
 
+    /// take_body_stream takes body by id as stream to send out, such as fetch request body.
+    /// If body is writable, it closes the sender, so data written before is sent and no more data can be written.
+    /// Guest is blocked while fetching, so it can not write more data to the body anyway.
+    /// If body is partially read, the rest buffer is sent before the rest stream.
+    pub fn take_body_stream(&mut self, id: u32) -> Option<Body> {
+        self.set_sender_closed(id);
+        if let Some(body) = self.body_map.remove(&id) {
+            return Some(body);
+        }
+        let stream = self.body_stream_map.remove(&id)?;
+        let buffer = self.body_buffer_map.remove(&id).unwrap_or_default();
+        let buffer =
+            futures_util::stream::iter(vec![Ok::<Bytes, axum::Error>(Bytes::from(buffer))]);
+        Some(Body::from_stream(buffer.chain(stream)))
+    }
+
     /// read_body reads body by id
     pub async fn read_body(
         &mut self,
@@ -166,6 +192,36 @@ impl HostContext {
         handle
     }
 
+    /// set_fetch_limit sets max fetch requests count
+    pub fn set_fetch_limit(&mut self, limit: Option<u32>) {
+        self.fetch_limit = limit;
+    }
+
+    /// can_fetch checks whether fetch count is under fetch limit
+    pub fn can_fetch(&self) -> bool {
+        !matches!(self.fetch_limit, Some(limit) if self.fetch_count >= limit)
+    }
+
+    /// incr_fetch_count increases fetch count
+    pub fn incr_fetch_count(&mut self) {
+        self.fetch_count += 1;
+    }
+
+    /// fetch_count returns fetch requests count
+    pub fn fetch_count(&self) -> u32 {
+        self.fetch_count
+    }
+
+    /// set_deploy_id sets deployment id of current request
+    pub fn set_deploy_id(&mut self, deploy_id: String) {
+        self.deploy_id = deploy_id;
+    }
+
+    /// deploy_id returns deployment id of current request
+    pub fn deploy_id(&self) -> &str {
+        &self.deploy_id
+    }
+
     /// elapsed returns the elapsed time in milliseconds
     pub fn elapsed(&self) -> tokio::time::Duration {
         self.created_at.elapsed()
@@ -2,11 +2,16 @@ use super::client::get_client;
 use super::host::land::http::fetching::{Host, Request, RequestError, RequestOptions, Response};
 use super::host::land::http::types::RedirectPolicy;
 use super::HostContext;
-use axum::body::Body;
-use reqwest::redirect;
+use crate::egress::{self, EgressError};
+use axum::body::{Body, Bytes, HttpBody};
+use futures_util::StreamExt;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_LENGTH};
 use std::str::FromStr;
 use tracing::{debug, warn};
 
+/// MAX_BUFFERED_BODY_SIZE is the max size of request body with known size to send as bytes
+const MAX_BUFFERED_BODY_SIZE: u64 = 10 * 1024 * 1024;
+
 impl Default for RequestOptions {
     fn default() -> Self {
         RequestOptions {
@@ -16,19 +21,6 @@ impl Default for RequestOptions {
     }
 }
 
-impl TryFrom<RedirectPolicy> for redirect::Policy {
-    type Error = anyhow::Error;
-    fn try_from(value: RedirectPolicy) -> Result<Self, Self::Error> {
-        match value {
-            RedirectPolicy::Follow => Ok(redirect::Policy::default()),
-            RedirectPolicy::Error => Ok(redirect::Policy::custom(|attempt| {
-                attempt.error(anyhow::anyhow!("redirect policy is error"))
-            })),
-            RedirectPolicy::Manual => Ok(redirect::Policy::none()),
-        }
-    }
-}
-
 #[async_trait::async_trait]
 impl Host for HostContext {
     async fn send_request(
@@ -39,67 +31,122 @@ impl Host for HostContext {
         let st = tokio::time::Instant::now();
         debug!(method = request.method, uri = request.uri, "Fetch start");
 
-        // read body
-        let body = match self.take_body(request.body.unwrap_or(0)) {
-            Some(b) => b,
-            None => Body::empty(),
-        };
+        // check fetch limit before validating, no dns lookup if fetch is not allowed
+        if !self.can_fetch() {
+            warn!(
+                method = request.method,
+                uri = request.uri,
+                "Fetch failed: too many requests"
+            );
+            return Err(RequestError::TooManyRequests);
+        }
+        let method = reqwest::Method::from_str(request.method.as_str()).map_err(|e| {
+            warn!(
+                method = request.method,
+                uri = request.uri,
+                "Fetch failed: invalid method: {e}"
+            );
+            RequestError::InvalidRequest(format!("Invalid method: {}", request.method))
+        })?;
+        let url = reqwest::Url::parse(request.uri.as_str()).map_err(|e| {
+            warn!(
+                method = request.method,
+                uri = request.uri,
+                "Fetch failed: invalid url: {e}"
+            );
+            RequestError::InvalidUrl
+        })?;
+        // check egress policy
+        if let Err(e) = egress::check_url(self.deploy_id(), &url).await {
+            warn!(
+                method = request.method,
+                uri = request.uri,
+                "Fetch failed: {e}"
+            );
+            return Err(e.into());
+        }
+        let headers = build_headers(&request.headers).map_err(|e| {
+            warn!(
+                method = request.method,
+                uri = request.uri,
+                "Fetch failed: {e}"
+            );
+            e
+        })?;
 
-        // FIXME: read body bytes is not correct, use stream instead
-        let body_bytes = axum::body::to_bytes(body, usize::MAX)
-            .await
-            .map_err(|e| {
-                warn!(
-                    method = request.method,
-                    uri = request.uri,
-                    "Fetch failed: {e}"
-                );
-                RequestError::InvalidRequest(e.to_string())
-            })?;
+        // count fetch after validating, invalid or denied requests do not use up the limit
+        self.incr_fetch_count();
 
-        let client = get_client(options.redirect);
-        // call fetch
-        let fetch_result = client
-            .request(
-                reqwest::Method::from_str(request.method.as_str()).unwrap(),
-                request.uri.clone(),
-            )
-            .timeout(std::time::Duration::from_secs(options.timeout as u64))
-            .body(body_bytes)
-            .send()
-            .await;
+        // take body, stream it if it is not buffered in memory
+        let body = match request.body {
+            Some(handle) => self.take_body_stream(handle),
+            None => None,
+        };
 
-        // handle fetch result failed
-        if fetch_result.is_err() {
-            let e = fetch_result.err().unwrap();
+        let client = get_client(self.deploy_id(), options.redirect).map_err(|e| {
+            warn!(
+                method = request.method,
+                uri = request.uri,
+                "Fetch failed: build client: {e}"
+            );
+            RequestError::InvalidRequest(format!("Fetch failed: {e}"))
+        })?;
+        // call fetch
+        let mut builder = client
+            .request(method, url)
+            .headers(headers)
+            .timeout(std::time::Duration::from_secs(options.timeout as u64));
+        if let Some(body) = body {
+            builder = match body.size_hint().exact() {
+                // body written by guest is fully buffered, send it as bytes with content-length
+                Some(size) if size <= MAX_BUFFERED_BODY_SIZE => {
+                    let bytes = axum::body::to_bytes(body, size as usize)
+                        .await
+                        .map_err(|e| {
+                            warn!(
+                                method = request.method,
+                                uri = request.uri,
+                                "Fetch read body failed: {e}"
+                            );
+                            RequestError::InvalidRequest(format!("Read body failed: {e}"))
+                        })?;
+                    builder.body(bytes)
+                }
+                // large body with known size is streamed with content-length, not chunked
+                Some(size) => builder.header(CONTENT_LENGTH, size).body(stream_body(body)),
+                None => builder.body(stream_body(body)),
+            };
+        }
+        let fetch_response = builder.send().await.map_err(|e| {
             let content = e.to_string();
             warn!(
                 method = request.method,
                 uri = request.uri,
                 "Fetch failed: {content}"
             );
+            // check if denied by egress policy when resolving
+            if let Some(e) = find_egress_error(&e) {
+                return RequestError::from(e);
+            }
+            if e.is_timeout() {
+                return RequestError::Timeout;
+            }
             // check if network error
-            if content.contains("connect") {
-                return Err(RequestError::NetworkError(content));
+            if e.is_connect() || content.contains("connect") {
+                return RequestError::NetworkError(content);
             }
-            return Err(RequestError::InvalidRequest(format!(
-                "Fetch failed: {content}"
-            )));
-        }
+            RequestError::InvalidRequest(format!("Fetch failed: {content}"))
+        })?;
 
-        let fetch_response = fetch_result.unwrap();
         let mut resp_headers = vec![];
-        // if body is stream, header should not contain content-length, use Transfer-Encoding:chunk
-        let mut is_stream = true;
-        let mut content_length: usize = 0;
         for (key, value) in fetch_response.headers() {
-            if key == "content-length" {
-                is_stream = false;
-                content_length = value.to_str().unwrap().parse().unwrap();
-            }
             let header_value = String::from_utf8_lossy(value.as_bytes()).to_string();
             resp_headers.push((key.to_string(), header_value));
         }
+        // if body is stream, header should not contain content-length, use Transfer-Encoding:chunk
+        let content_length = fetch_response.content_length();
+        let is_stream = content_length.is_none();
+        let content_length = content_length.unwrap_or_default();
 
         let status = fetch_response.status().as_u16();
         let body_handle = if is_stream {
@@ -107,7 +154,14 @@ impl Host for HostContext {
             let body = Body::from_stream(body_stream);
             self.set_body(0, body)
         } else {
-            let body = fetch_response.bytes().await.unwrap();
+            let body = fetch_response.bytes().await.map_err(|e| {
+                warn!(
+                    method = request.method,
+                    uri = request.uri,
+                    "Fetch read body failed: {e}"
+                );
+                RequestError::NetworkError(e.to_string())
+            })?;
             let body = Body::from(body);
             self.set_body(0, body)
         };
@@ -136,3 +190,64 @@ impl Host for HostContext {
         Ok(resp)
     }
 }
+
+/// build_headers converts request headers to reqwest HeaderMap
+fn build_headers(headers: &[(String, String)]) -> Result<HeaderMap, RequestError> {
+    let mut map = HeaderMap::new();
+    for (key, value) in headers {
+        let name = HeaderName::from_str(key)
+            .map_err(|_| RequestError::InvalidRequest(format!("Invalid header name: {}", key)))?;
+        let value = HeaderValue::from_str(value).map_err(|_| {
+            RequestError::InvalidRequest(format!("Invalid header value of {}", key))
+        })?;
+        map.append(name, value);
+    }
+    Ok(map)
+}
+
+/// stream_body converts axum body to reqwest body.
+/// reqwest requires Sync stream, so forward body chunks through a channel.
+fn stream_body(body: Body) -> reqwest::Body {
+    let (sender, receiver) = tokio::sync::mpsc::channel::<Result<Bytes, axum::Error>>(4);
+    tokio::spawn(async move {
+        let mut stream = body.into_data_stream();
+        while let Some(chunk) = stream.next().await {
+            if sender.send(chunk).await.is_err() {
+                // receiver dropped, request is finished or canceled
+                return;
+            }
+        }
+    });
+    let stream = futures_util::stream::unfold(receiver, |mut receiver| async move {
+        receiver.recv().await.map(|chunk| (chunk, receiver))
+    });
+    reqwest::Body::wrap_stream(stream)
+}
+
+impl From<&EgressError> for RequestError {
+    fn from(e: &EgressError) -> Self {
+        match e {
+            EgressError::InvalidUrl => RequestError::InvalidUrl,
+            EgressError::NotAllowed(_) => RequestError::DestinationNotAllowed,
+            EgressError::Resolve(msg) => RequestError::NetworkError(msg.clone()),
+        }
+    }
+}
+
+impl From<EgressError> for RequestError {
+    fn from(e: EgressError) -> Self {
+        RequestError::from(&e)
+    }
+}
+
+/// find_egress_error finds egress error in reqwest error sources
+fn find_egress_error(e: &reqwest::Error) -> Option<&EgressError> {
+    let mut source = std::error::Error::source(e);
+    while let Some(err) = source {
+        if let Some(e) = err.downcast_ref::<EgressError>() {
+            return Some(e);
+        }
+        source = err.source();
+    }
+    None
+}
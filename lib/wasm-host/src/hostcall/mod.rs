@@ -6,7 +6,7 @@ mod fetch;
 mod guest;
 mod host;
 
-pub use client::init_clients;
+pub use client::retain_clients;
 pub use context::HostContext;
 pub use guest::exports::land::http::incoming::{Request, Response};
 pub use guest::HttpHandlerPre;
@@ -1,10 +1,14 @@
+pub mod aot;
+pub mod egress;
+pub mod envs;
 pub mod hostcall;
+pub mod logs;
 pub mod pool;
 
 mod context;
 mod engine;
 mod worker;
 
-pub use context::Context;
-pub use engine::init_engines;
+pub use context::{Context, LimitError, Limits, Stats};
+pub use engine::{engine_opts, fingerprint, init_engines, EngineOpts, MODULE_VERSION};
 pub use worker::Worker;
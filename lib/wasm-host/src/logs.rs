@@ -0,0 +1,204 @@
+use axum::body::Bytes;
+use lazy_static::lazy_static;
+use metrics::counter;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+use wasmtime_wasi::{HostOutputStream, StdoutStream, StreamResult, Subscribe};
+
+/// MAX_REQUEST_LOG_SIZE is max bytes of guest logs of one request
+pub const MAX_REQUEST_LOG_SIZE: usize = 64 * 1024;
+
+/// MAX_QUEUE_SIZE is max count of log entries waiting to be shipped,
+/// oldest entries are dropped if the queue is full
+pub const MAX_QUEUE_SIZE: usize = 10000;
+
+/// Line is one line of guest stdout or stderr
+#[derive(Clone, Debug)]
+pub struct Line {
+    pub stream: &'static str,
+    pub message: String,
+    pub timestamp: i64, // unix timestamp in milliseconds
+}
+
+#[derive(Default)]
+struct Buffer {
+    lines: Vec<Line>,
+    pending: [Vec<u8>; 2],
+    size: usize,
+    truncated: bool,
+}
+
+impl Buffer {
+    fn write(&mut self, stream: usize, bytes: &[u8]) {
+        if self.truncated {
+            return;
+        }
+        self.pending[stream].extend_from_slice(bytes);
+        while let Some(pos) = self.pending[stream].iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.pending[stream].drain(..=pos).collect();
+            self.push(stream, &line[..line.len() - 1]);
+        }
+        // unfinished lines count to the limit too, guest may write without newlines
+        let pending_size = self.pending[0].len() + self.pending[1].len();
+        if self.size + pending_size > MAX_REQUEST_LOG_SIZE {
+            self.truncated = true;
+            self.pending = Default::default();
+        }
+    }
+    fn push(&mut self, stream: usize, line: &[u8]) {
+        if self.truncated {
+            return;
+        }
+        if self.size + line.len() > MAX_REQUEST_LOG_SIZE {
+            self.truncated = true;
+            return;
+        }
+        self.size += line.len();
+        self.lines.push(Line {
+            stream: if stream == 0 { "stdout" } else { "stderr" },
+            message: String::from_utf8_lossy(line).trim_end().to_string(),
+            timestamp: now_millis(),
+        });
+    }
+}
+
+/// now_millis returns current unix timestamp in milliseconds
+pub fn now_millis() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_default()
+}
+
+/// Logs captures guest stdout and stderr of one request into a bounded buffer
+#[derive(Clone, Default)]
+pub struct Logs {
+    buffer: Arc<Mutex<Buffer>>,
+}
+
+impl Logs {
+    /// stdout returns pipe of guest stdout
+    pub fn stdout(&self) -> LogPipe {
+        LogPipe {
+            stream: 0,
+            buffer: self.buffer.clone(),
+        }
+    }
+    /// stderr returns pipe of guest stderr
+    pub fn stderr(&self) -> LogPipe {
+        LogPipe {
+            stream: 1,
+            buffer: self.buffer.clone(),
+        }
+    }
+    /// take takes captured lines, unfinished lines are flushed
+    pub fn take(&self) -> Vec<Line> {
+        let mut buffer = self.buffer.lock().unwrap();
+        for stream in 0..2 {
+            let line = std::mem::take(&mut buffer.pending[stream]);
+            if !line.is_empty() {
+                buffer.push(stream, &line);
+            }
+        }
+        let mut lines = std::mem::take(&mut buffer.lines);
+        if buffer.truncated {
+            lines.push(Line {
+                stream: "stderr",
+                message: "...(log truncated)".to_string(),
+                timestamp: now_millis(),
+            });
+        }
+        *buffer = Buffer::default();
+        lines
+    }
+}
+
+/// LogPipe writes guest output to logs buffer,
+/// it never blocks or fails guest, output over limit is discarded
+pub struct LogPipe {
+    stream: usize,
+    buffer: Arc<Mutex<Buffer>>,
+}
+
+impl StdoutStream for LogPipe {
+    fn stream(&self) -> Box<dyn HostOutputStream> {
+        Box::new(LogPipe {
+            stream: self.stream,
+            buffer: self.buffer.clone(),
+        })
+    }
+    fn isatty(&self) -> bool {
+        false
+    }
+}
+
+impl HostOutputStream for LogPipe {
+    fn write(&mut self, bytes: Bytes) -> StreamResult<()> {
+        self.buffer.lock().unwrap().write(self.stream, &bytes);
+        Ok(())
+    }
+    fn flush(&mut self) -> StreamResult<()> {
+        Ok(())
+    }
+    fn check_write(&mut self) -> StreamResult<usize> {
+        Ok(MAX_REQUEST_LOG_SIZE)
+    }
+}
+
+#[async_trait::async_trait]
+impl Subscribe for LogPipe {
+    async fn ready(&mut self) {}
+}
+
+/// Entry is a log line tagged with request, project and deployment, shipped to server
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Entry {
+    pub req_id: String,
+    pub project_id: i32,
+    pub deploy_id: i32,
+    pub stream: String,
+    pub message: String,
+    pub timestamp: i64,
+}
+
+lazy_static! {
+    static ref QUEUE: Mutex<VecDeque<Entry>> = Mutex::new(VecDeque::new());
+}
+
+/// push adds lines of a request to the shipping queue
+pub fn push(req_id: &str, project_id: i32, deploy_id: i32, lines: Vec<Line>) {
+    let mut queue = QUEUE.lock().unwrap();
+    for line in lines {
+        if queue.len() >= MAX_QUEUE_SIZE {
+            queue.pop_front();
+            counter!("guest_logs_dropped_total").increment(1);
+        }
+        queue.push_back(Entry {
+            req_id: req_id.to_string(),
+            project_id,
+            deploy_id,
+            stream: line.stream.to_string(),
+            message: line.message,
+            timestamp: line.timestamp,
+        });
+    }
+}
+
+/// drain takes at most max_count entries from the shipping queue until messages size exceeds max_bytes,
+/// it takes one entry at least if the queue is not empty
+pub fn drain(max_bytes: usize, max_count: usize) -> Vec<Entry> {
+    let mut queue = QUEUE.lock().unwrap();
+    let mut entries = vec![];
+    let mut size = 0;
+    while let Some(entry) = queue.front() {
+        size += entry.message.len();
+        if (size > max_bytes || entries.len() >= max_count) && !entries.is_empty() {
+            break;
+        }
+        entries.push(queue.pop_front().unwrap());
+    }
+    entries
+}
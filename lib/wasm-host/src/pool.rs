@@ -1,65 +1,201 @@
-use crate::{engine::MODULE_VERSION, worker::Worker};
+use crate::{engine::fingerprint, worker::Worker};
 use anyhow::{anyhow, Result};
-use lazy_static::lazy_static;
-use moka::sync::Cache;
+use metrics::{counter, gauge, histogram};
+use moka::{notification::RemovalCause, sync::Cache};
 use once_cell::sync::OnceCell;
-use std::time::Duration;
+use std::{sync::Arc, time::Duration};
 use tokio::time::Instant;
 use tracing::{debug, info, warn};
 
 /// FILE_DIR is the directory of wasm files
 pub static FILE_DIR: OnceCell<String> = OnceCell::new();
 
-lazy_static! {
-    pub static ref WASM_INSTANCES: Cache<String,Worker > = Cache::builder()
-    // Time to live (TTL): 3 hours
-    .time_to_live(Duration::from_secs(3 * 60 * 60))
-    // Time to idle (TTI):  60 minutes
-    .time_to_idle(Duration::from_secs(60 * 60))
-    // Create the cache.
-    .build();
+/// DEFAULT_MAX_WORKERS is the default max count of cached workers
+pub const DEFAULT_MAX_WORKERS: u64 = 1000;
+
+/// CachedWorker is the worker of a deployment with its wasm module
+#[derive(Clone)]
+struct CachedWorker {
+    module: String,
+    worker: Worker,
 }
 
-async fn prepare_wasm_worker(key: &str, is_aot: bool) -> Result<Worker> {
-    let st = Instant::now();
-    let dir = FILE_DIR.get().unwrap();
-    let real_file = format!("{}/{}", dir, key);
+/// WORKERS caches workers keyed by deployment id
+static WORKERS: OnceCell<Cache<String, CachedWorker>> = OnceCell::new();
+
+/// init_cache creates workers cache with max entries, it should be called before preparing workers
+pub fn init_cache(max_entries: u64) {
+    let _ = WORKERS.set(build_cache(max_entries));
+}
+
+fn build_cache(max_entries: u64) -> Cache<String, CachedWorker> {
+    debug!("Create workers cache, max entries: {}", max_entries);
+    Cache::builder()
+        .max_capacity(max_entries)
+        // Time to idle (TTI):  60 minutes
+        .time_to_idle(Duration::from_secs(60 * 60))
+        .eviction_listener(|key: Arc<String>, _, cause| {
+            // replaced worker is counted as insert
+            if cause == RemovalCause::Replaced {
+                return;
+            }
+            let cause = format!("{:?}", cause).to_lowercase();
+            debug!("Evict worker: {}, cause: {}", key, cause);
+            counter!("worker_cache_total", "typ" => "evict", "cause" => cause).increment(1);
+        })
+        .build()
+}
+
+fn workers() -> &'static Cache<String, CachedWorker> {
+    WORKERS.get_or_init(|| build_cache(DEFAULT_MAX_WORKERS))
+}
+
+/// aot_file_path returns aot file path of wasm file with engine fingerprint,
+/// so aot artifacts of different engine config are not mixed
+pub fn aot_file_path(path: &str) -> Result<String> {
+    let suffix = format!(".wasm.{}.aot", fingerprint()?);
+    Ok(path.replace(".wasm", &suffix))
+}
+
+/// wasm_file_path returns canonical path of wasm file, it must be inside FILE_DIR
+fn wasm_file_path(key: &str) -> Result<String> {
+    wasm_file_path_in(FILE_DIR.get().unwrap(), key)
+}
 
-    if !std::path::Path::new(&real_file).exists() {
+/// wasm_file_path_in returns canonical path of wasm file, it must be inside dir
+fn wasm_file_path_in(dir: &str, key: &str) -> Result<String> {
+    let real_file = format!("{}/{}", dir, key);
+    let Ok(real_path) = std::fs::canonicalize(&real_file) else {
         warn!("Wasm file not found: {}", real_file);
         return Err(anyhow!("Function is not found"));
+    };
+    let dir_path = std::fs::canonicalize(dir)?;
+    if !real_path.starts_with(&dir_path) || !real_path.is_file() {
+        warn!("Wasm file is invalid: {}", real_file);
+        return Err(anyhow!("Function is not found"));
     }
+    Ok(real_path.to_string_lossy().to_string())
+}
 
-    // create wasm worker pool
+/// load_worker loads worker of wasm module and caches it by deployment id
+async fn load_worker(deploy_id: &str, module: &str, is_aot: bool) -> Result<Worker> {
+    if !module.ends_with(".wasm") {
+        return Err(anyhow!("Invalid key"));
+    }
+    let st = Instant::now();
+    let real_file = wasm_file_path(module)?;
     let worker = Worker::new(&real_file, is_aot).await?;
-    WASM_INSTANCES.insert(key.to_string(), worker);
+    let cache = workers();
+    cache.insert(
+        deploy_id.to_string(),
+        CachedWorker {
+            module: module.to_string(),
+            worker: worker.clone(),
+        },
+    );
+    counter!("worker_cache_total", "typ" => "insert").increment(1);
+    gauge!("worker_cache_entries").set(cache.entry_count() as f64);
+    // aot means the worker is deserialized from aot file, jit means it is compiled from wasm
+    let typ = if worker.is_aot() { "aot" } else { "jit" };
+    counter!("worker_load_total", "typ" => typ).increment(1);
+    histogram!("worker_load_ms", "typ" => typ).record(st.elapsed().as_secs_f64() * 1000.0);
+    info!(elapsed = %st.elapsed().as_millis(), deploy_id, "Worker created");
+    Ok(worker)
+}
+
+/// prepare_worker gets cached worker of deployment,
+/// it loads the worker if it is not cached or the wasm module is changed.
+/// It returns true as cold start if the worker is loaded.
+pub async fn prepare_worker(deploy_id: &str, module: &str, is_aot: bool) -> Result<(Worker, bool)> {
+    if let Some(cached) = workers().get(deploy_id) {
+        if cached.module == module {
+            counter!("worker_cache_total", "typ" => "hit").increment(1);
+            return Ok((cached.worker, false));
+        }
+    }
+    counter!("worker_cache_total", "typ" => "miss").increment(1);
+    let worker = load_worker(deploy_id, module, is_aot).await?;
+    Ok((worker, true))
+}
 
-    let instances_pool = WASM_INSTANCES.get(key);
-    info!(elapsed = %st.elapsed().as_millis(),"Worker created");
+/// insert_worker loads worker of deployment and replaces the cached one,
+/// it is called before routing requests to the deployment, so requests never hit a cold start
+pub async fn insert_worker(deploy_id: &str, module: &str, is_aot: bool) -> Result<Worker> {
+    load_worker(deploy_id, module, is_aot).await
+}
 
-    Ok(instances_pool.unwrap())
+/// evict_worker evicts cached worker of deployment
+pub fn evict_worker(deploy_id: &str) {
+    let cache = workers();
+    cache.invalidate(deploy_id);
+    gauge!("worker_cache_entries").set(cache.entry_count() as f64);
+    debug!("Evict worker: {}", deploy_id);
 }
 
-pub async fn prepare_worker(key: &str, is_aot: bool) -> Result<Worker> {
-    let instances_pool = WASM_INSTANCES.get(key);
-    if let Some(instance) = instances_pool {
-        return Ok(instance);
+/// remove_worker evicts cached worker of deployment, and removes wasm and aot files if remove_files is true
+pub fn remove_worker(deploy_id: &str, key: &str, remove_files: bool) -> Result<()> {
+    evict_worker(deploy_id);
+    if !remove_files {
+        return Ok(());
     }
-    if key.ends_with(".wasm") {
-        return prepare_wasm_worker(key, is_aot).await;
+    let real_file = match wasm_file_path(key) {
+        Ok(f) => f,
+        Err(_) => return Ok(()), // file is not exist
+    };
+    crate::aot::remove(&aot_file_path(&real_file)?)?;
+    if std::path::Path::new(&real_file).exists() {
+        std::fs::remove_file(&real_file)?;
+        info!("Remove file: {}", real_file);
     }
-    Err(anyhow!("Invalid key"))
+    Ok(())
 }
 
 /// compile_aot compile aot wasm
 pub async fn compile_aot(path: &str) -> Result<()> {
-    let suffix = format!(".wasm.{}.aot", MODULE_VERSION);
-    let aot_path = path.replace(".wasm", &suffix);
+    let aot_path = aot_file_path(path)?;
     if std::path::Path::new(&aot_path).exists() {
-        debug!("AOT file already exists: {}", &aot_path);
-        return Ok(());
+        match crate::aot::read_verified(path, &aot_path) {
+            Ok(_) => {
+                debug!("AOT file already exists: {}", &aot_path);
+                return Ok(());
+            }
+            Err(e) => warn!("AOT file is invalid: {}, file: {}", e, aot_path),
+        }
     }
     Worker::compile_aot(path, &aot_path)?;
     debug!("Compile AOT success: {}", &aot_path);
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::wasm_file_path_in;
+
+    #[test]
+    fn test_wasm_file_path() {
+        let root = tempfile::tempdir().unwrap();
+        let dir = root.path().join("data");
+        std::fs::create_dir_all(dir.join("project")).unwrap();
+        std::fs::write(dir.join("project/a.wasm"), b"wasm").unwrap();
+        std::fs::write(root.path().join("outside.wasm"), b"wasm").unwrap();
+        let dir = dir.to_str().unwrap();
+
+        assert!(wasm_file_path_in(dir, "project/a.wasm").is_ok());
+        assert!(wasm_file_path_in(dir, "project/../project/a.wasm").is_ok());
+        // traversal out of dir
+        assert!(wasm_file_path_in(dir, "../outside.wasm").is_err());
+        assert!(wasm_file_path_in(dir, "project/../../outside.wasm").is_err());
+        // absolute path is joined under dir, not used as is
+        let outside = root.path().join("outside.wasm");
+        assert!(wasm_file_path_in(dir, outside.to_str().unwrap()).is_err());
+        // directory is not a wasm file
+        assert!(wasm_file_path_in(dir, "project").is_err());
+        // symlink inside dir pointing outside
+        #[cfg(unix)]
+        {
+            let link = root.path().join("data/project/link.wasm");
+            std::os::unix::fs::symlink(&outside, link).unwrap();
+            assert!(wasm_file_path_in(dir, "project/link.wasm").is_err());
+        }
+    }
+}
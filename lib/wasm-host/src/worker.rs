@@ -1,7 +1,8 @@
-use crate::hostcall::HttpHandlerPre;
+use crate::{hostcall::HttpHandlerPre, LimitError, Stats};
 use anyhow::Result;
 use axum::body::Body;
-use tracing::debug;
+use std::time::Instant;
+use tracing::{debug, warn};
 use wasmtime::{
     component::{Component, Linker},
     Engine, Store, UpdateDeadline,
@@ -13,6 +14,7 @@ pub struct Worker {
     path: String,
     engine: Engine,
     instance_pre: HttpHandlerPre<crate::context::Context>,
+    aot: bool,
 }
 
 impl std::fmt::Debug for Worker {
@@ -24,6 +26,13 @@ impl std::fmt::Debug for Worker {
 impl Worker {
     // from_binary is used to create worker from bytes
     pub async fn from_binary(bytes: &[u8], path: Option<String>) -> Result<Self> {
+        let bytes = bytes.to_vec();
+        // compile in blocking thread, it takes seconds for large component
+        tokio::task::spawn_blocking(move || Self::compile(&bytes, path)).await?
+    }
+
+    /// compile compiles wasm component and links host functions
+    fn compile(bytes: &[u8], path: Option<String>) -> Result<Self> {
         let engine = crate::engine::get("default")?;
         let component = Component::from_binary(&engine, bytes)?;
         debug!("Load wasm component from binary, size:{}", bytes.len());
@@ -40,12 +49,14 @@ impl Worker {
             path: path.unwrap_or("binary".to_string()),
             engine,
             instance_pre: HttpHandlerPre::new(instance_pre)?,
+            aot: false,
         })
     }
 
-    async fn from_aot(path: String) -> Result<Self> {
+    /// from_aot loads worker from aot file, the file is verified by manifest before deserializing
+    async fn from_aot(wasm_path: &str, path: String) -> Result<Self> {
         let engine = crate::engine::get("default")?;
-        let bytes = std::fs::read(&path)?;
+        let bytes = crate::aot::read_verified(wasm_path, &path)?;
         debug!(
             "Load wasm component from AOT file: {}, size: {}",
             path,
@@ -66,15 +77,24 @@ impl Worker {
             path,
             engine,
             instance_pre: HttpHandlerPre::new(instance_pre)?,
+            aot: true,
         })
     }
 
+    /// precompile compiles wasm component bytes to aot artifact with default engine
+    pub fn precompile(bytes: &[u8]) -> Result<Vec<u8>> {
+        let engine = super::engine::get("default")?;
+        let component = Component::from_binary(&engine, bytes)?;
+        component.serialize()
+    }
+
     pub fn compile_aot(src: &str, dst: &str) -> Result<()> {
         let engine = super::engine::get("default")?;
         let component = Component::from_file(&engine, src)?;
         let bytes = Component::serialize(&component)?;
         debug!("Write AOT from {} to {}, size: {}", src, dst, bytes.len());
-        std::fs::write(dst, bytes)?;
+        std::fs::write(dst, &bytes)?;
+        crate::aot::write_manifest(src, dst, &bytes)?;
         Ok(())
     }
 
@@ -84,10 +104,17 @@ impl Worker {
 
         // compile aot wasm
         if is_aot {
-            let suffix = format!(".wasm.{}.aot", crate::engine::MODULE_VERSION);
-            let aot_path = path.replace(".wasm", &suffix);
+            let aot_path = crate::pool::aot_file_path(path)?;
             if std::path::Path::new(&aot_path).exists() {
-                return Self::from_aot(aot_path).await;
+                // aot file may be compiled by incompatible engine or tampered,
+                // recompile it locally
+                match Self::from_aot(path, aot_path.clone()).await {
+                    Ok(worker) => return Ok(worker),
+                    Err(e) => {
+                        warn!("Load AOT failed: {}, file: {}", e, aot_path);
+                        crate::aot::remove(&aot_path)?;
+                    }
+                }
             }
             let path2 = path.to_string();
             std::thread::spawn(move || {
@@ -100,31 +127,70 @@ impl Worker {
         Self::from_binary(&binary, Some(path.to_string())).await
     }
 
+    /// is_aot returns whether the worker is loaded from aot file
+    pub fn is_aot(&self) -> bool {
+        self.aot
+    }
+
     /// handle_request is used to handle http request
     pub async fn handle_request(
         &self,
         req: crate::hostcall::Request,
         context: crate::context::Context,
     ) -> Result<(crate::hostcall::Response, Body)> {
+        self.handle_request_with_stats(req, context).await.0
+    }
+
+    /// handle_request_with_stats handles http request and returns the resources it used,
+    /// stats are returned even if the request fails
+    pub async fn handle_request_with_stats(
+        &self,
+        req: crate::hostcall::Request,
+        context: crate::context::Context,
+    ) -> (Result<(crate::hostcall::Response, Body)>, Stats) {
+        let wall_time = context.limits().wall_time;
+
         // create store
         let mut store = Store::new(&self.engine, context);
         store.set_epoch_deadline(1);
-        store.epoch_deadline_callback(move |store| {
+        store.epoch_deadline_callback(move |mut store| {
+            // each callback means guest runs one more epoch tick
+            let ctx = store.data_mut();
+            ctx.add_cpu_tick()?;
             debug!(
-                "epoch_deadline_callback, cost:{:.2?}",
-                store.data().elapsed()
+                "epoch_deadline_callback, cost:{:.2?}, cpu:{:.2?}",
+                ctx.elapsed(),
+                ctx.cpu_time(),
             );
             Ok(UpdateDeadline::Yield(1))
         });
         store.limiter(|ctx| &mut ctx.limiter);
 
         // get exports and call handle_request
-        let exports = self.instance_pre.instantiate_async(&mut store).await?;
-        let resp = exports
-            .land_http_incoming()
-            .call_handle_request(&mut store, &req)
-            .await?;
-        let body = store.data_mut().take_body(resp.body.unwrap()).unwrap();
-        Ok((resp, body))
+        let st = Instant::now();
+        let mut instantiate = None;
+        let call = async {
+            let exports = self.instance_pre.instantiate_async(&mut store).await?;
+            instantiate = Some(st.elapsed());
+            exports
+                .land_http_incoming()
+                .call_handle_request(&mut store, &req)
+                .await
+        };
+        let result = match wall_time {
+            Some(wall_time) => tokio::time::timeout(wall_time, call)
+                .await
+                .unwrap_or_else(|_| Err(LimitError::WallTimeExceeded(wall_time).into())),
+            None => call.await,
+        };
+        let mut stats = store.data().stats();
+        // instantiate and execute time are none if instantiating fails
+        stats.instantiate = instantiate;
+        stats.execute = instantiate.map(|instantiate| st.elapsed().saturating_sub(instantiate));
+        let result = result.map(|resp| {
+            let body = store.data_mut().take_body(resp.body.unwrap()).unwrap();
+            (resp, body)
+        });
+        (result, stats)
     }
 }
@@ -1,4 +1,5 @@
 use crate::{
+    inspect,
     middle::{WorkerInfo, WorkerMetrics},
     ServerError, ENABLE_WASMTIME_AOT, ENDPOINT_NAME,
 };
@@ -10,7 +11,9 @@ use axum::{
     response::{IntoResponse, Response},
     Extension,
 };
-use land_wasm_host::{hostcall, pool::prepare_worker, Context, Worker};
+use land_wasm_host::{
+    envs, hostcall, logs, pool::prepare_worker, Context, LimitError, Stats, Worker,
+};
 use std::net::SocketAddr;
 use tokio::time::Instant;
 use tracing::{debug, info, info_span, warn, Instrument};
@@ -49,7 +52,10 @@ pub async fn run(
 
     // call wasm async
     async move {
-        let result = wasm(req, &info).await;
+        let result = wasm(req, &info, &metrics).await;
+        metrics
+            .req_fn_duration_ms
+            .record(st.elapsed().as_secs_f64() * 1000.0);
         if let Err(err) = result {
             let elapsed = st.elapsed().as_micros();
             warn!(
@@ -79,9 +85,25 @@ pub async fn run(
     .await
 }
 
-async fn wasm(req: Request<Body>, info: &WorkerInfo) -> Result<Response<Body>> {
+/// server_timing formats the resources that a request used as Server-Timing header
+fn server_timing(stats: &Stats) -> String {
+    format!(
+        "cpu;dur={}, total;dur={:.1}, mem;desc=\"{}\", fetch;desc=\"{}\"",
+        stats.cpu_time.as_millis(),
+        stats.elapsed.as_secs_f64() * 1000.0,
+        stats.memory,
+        stats.fetch_count
+    )
+}
+
+async fn wasm(
+    req: Request<Body>,
+    info: &WorkerInfo,
+    metrics: &WorkerMetrics,
+) -> Result<Response<Body>> {
     let req_id = info.req_id.clone();
-    let worker = init_worker(&info.wasm_module).await?;
+    let (worker, cold) = init_worker(&info.deploy_id, &info.wasm_module).await?;
+    metrics.record_start(cold);
 
     // convert request to host-call request
     let mut headers: Vec<(String, String)> = vec![];
@@ -103,17 +125,41 @@ async fn wasm(req: Request<Body>, info: &WorkerInfo) -> Result<Response<Body>> {
             .get("host")
             .and_then(|v| v.to_str().ok())
             .unwrap_or("unknown");
-        let new_uri = format!("http://{}{}", host, uri.path());
+        // keep query string, guest and inspector need it
+        let path_and_query = uri.path_and_query().map_or("/", |pq| pq.as_str());
+        let new_uri = format!("http://{}{}", host, path_and_query);
         uri = new_uri.parse().unwrap();
     }
     let method = req.method().clone();
-    // let envs = envs::get_by_project(ctx.project_uuid.clone()).await;
-    let mut context = Context::new(None);
+    // sample request to inspector if it is on for the deployment
+    let mut record = if inspect::is_enabled(&info.deploy_id) {
+        Some(inspect::Record {
+            req_id: req_id.clone(),
+            project_id: info.project_id.parse().unwrap_or_default(),
+            deploy_id: info.deploy_id.parse().unwrap_or_default(),
+            timestamp: logs::now_millis(),
+            method: method.to_string(),
+            uri: inspect::redact_uri(&uri.to_string()),
+            req_headers: inspect::redact_headers(req.headers()),
+            ..Default::default()
+        })
+    } else {
+        None
+    };
+    let envs = envs::get(&info.project_id);
+    let mut context = Context::new(envs);
+    context.set_limits(info.limits.clone());
+    context.host_ctx().set_deploy_id(info.deploy_id.clone());
     // if method is GET or DELETE, set body to None
     let body_handle = if method == "GET" || method == "DELETE" {
         0
     } else {
-        let body = req.into_body();
+        let mut body = req.into_body();
+        if let Some(record) = record.as_mut() {
+            let (new_body, preview) = capture_body(body).await;
+            body = new_body;
+            record.req_body = preview;
+        }
         context.set_body(0, body)
     };
     debug!("Set body_handle: {:?}", body_handle);
@@ -125,13 +171,36 @@ async fn wasm(req: Request<Body>, info: &WorkerInfo) -> Result<Response<Body>> {
         body: Some(body_handle),
     };
 
-    let (wasm_resp, wasm_resp_body) = match worker.handle_request(wasm_req, context).await {
+    let logs = context.logs();
+    let (result, stats) = worker.handle_request_with_stats(wasm_req, context).await;
+    ship_logs(info, logs.take());
+    metrics.record_stats(&stats);
+    let timing = server_timing(&stats);
+    let (wasm_resp, wasm_resp_body) = match result {
         Ok((wasm_resp, wasm_resp_body)) => (wasm_resp, wasm_resp_body),
         Err(e) => {
-            let builder = Response::builder().status(500);
+            // stopped by runtime limits, return 503
+            if let Some(limit_err) = e.downcast_ref::<LimitError>() {
+                warn!("Limit exceeded: {}", limit_err);
+                inspect_error(record, 503, limit_err.to_string(), &stats);
+                let builder = Response::builder()
+                    .status(503)
+                    .header("server-timing", timing);
+                return Ok(builder.body(Body::from(limit_err.to_string())).unwrap());
+            }
+            inspect_error(record, 500, format!("{:#}", e), &stats);
+            let builder = Response::builder()
+                .status(500)
+                .header("server-timing", timing);
             return Ok(builder.body(Body::from(e.to_string())).unwrap());
         }
     };
+    debug!(
+        cpu = ?stats.cpu_time,
+        mem = stats.memory,
+        fetch = stats.fetch_count,
+        "Stats"
+    );
 
     // convert host-call response to response
     let mut builder = Response::builder().status(wasm_resp.status);
@@ -142,15 +211,83 @@ async fn wasm(req: Request<Body>, info: &WorkerInfo) -> Result<Response<Body>> {
         builder = builder.header("x-request-id", req_id.clone());
     }
     builder = builder.header("x-served-by", ENDPOINT_NAME.get().unwrap());
+    builder = builder.header("server-timing", timing);
+    let mut wasm_resp_body = wasm_resp_body;
+    if let Some(mut record) = record {
+        let (new_body, preview) = capture_body(wasm_resp_body).await;
+        wasm_resp_body = new_body;
+        record.status = wasm_resp.status;
+        record.resp_headers = inspect::redact_headers(builder.headers_ref().unwrap());
+        record.resp_body = preview;
+        record.elapsed_ms = stats.elapsed.as_secs_f64() * 1000.0;
+        record.cpu_ms = stats.cpu_time.as_millis() as u64;
+        inspect::push(record);
+    }
     Ok(builder.body(wasm_resp_body).unwrap())
 }
 
-/// init_worker is a helper function to prepare wasm worker
-async fn init_worker(wasm_path: &str) -> Result<Worker> {
+/// capture_body reads body to preview text for inspector and rebuilds the body,
+/// streaming or large body is not captured.
+/// Inspector never fails the request, if reading fails, such as client disconnected,
+/// the body is not captured and left empty.
+async fn capture_body(body: Body) -> (Body, String) {
+    match body.size_hint().exact() {
+        Some(0) => (body, String::new()),
+        Some(size) if size <= inspect::MAX_CAPTURE_SIZE => {
+            match axum::body::to_bytes(body, size as usize).await {
+                Ok(bytes) => {
+                    let preview = inspect::body_preview(&bytes);
+                    (Body::from(bytes), preview)
+                }
+                Err(e) => {
+                    debug!("Capture body failed: {}", e);
+                    (Body::empty(), "(body not captured)".to_string())
+                }
+            }
+        }
+        Some(size) => (body, format!("(body not captured, {} bytes)", size)),
+        None => (body, "(streaming body not captured)".to_string()),
+    }
+}
+
+/// inspect_error records failed request to inspector
+fn inspect_error(record: Option<inspect::Record>, status: u16, error: String, stats: &Stats) {
+    if let Some(mut record) = record {
+        record.status = status;
+        record.error = Some(error);
+        record.elapsed_ms = stats.elapsed.as_secs_f64() * 1000.0;
+        record.cpu_ms = stats.cpu_time.as_millis() as u64;
+        inspect::push(record);
+    }
+}
+
+/// ship_logs queues guest logs of the request to ship to server
+fn ship_logs(info: &WorkerInfo, lines: Vec<logs::Line>) {
+    if lines.is_empty() {
+        return;
+    }
+    for line in lines.iter() {
+        debug!(stream = line.stream, "Guest: {}", line.message);
+    }
+    logs::push(
+        &info.req_id,
+        info.project_id.parse().unwrap_or_default(),
+        info.deploy_id.parse().unwrap_or_default(),
+        lines,
+    );
+}
+
+/// init_worker is a helper function to prepare wasm worker, it returns true if cold start
+async fn init_worker(deploy_id: &str, wasm_path: &str) -> Result<(Worker, bool)> {
     let aot_enable = ENABLE_WASMTIME_AOT.get().unwrap();
-    let worker = prepare_worker(wasm_path, *aot_enable)
-        .instrument(info_span!("[WASM]", wasm_path = %wasm_path))
+    let (worker, cold) = prepare_worker(deploy_id, wasm_path, *aot_enable)
+        .instrument(info_span!("[WASM]", deploy_id = %deploy_id, wasm_path = %wasm_path))
         .await?;
-    debug!("Wasm worker pool ok: {}", wasm_path);
-    Ok(worker)
+    debug!(
+        cold,
+        aot = worker.is_aot(),
+        "Wasm worker pool ok: {}",
+        wasm_path
+    );
+    Ok((worker, cold))
 }
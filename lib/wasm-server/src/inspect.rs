@@ -0,0 +1,122 @@
+use axum::http::HeaderMap;
+
+pub use land_common::inspect::{is_enabled, push, Record};
+
+/// MAX_BODY_SIZE is max bytes of request or response body kept in record
+pub const MAX_BODY_SIZE: usize = 4096;
+
+/// MAX_CAPTURE_SIZE is max body size to capture, larger or streaming bodies are not captured
+pub const MAX_CAPTURE_SIZE: u64 = 1024 * 1024;
+
+/// SENSITIVE_NAMES are header or query parameter names whose values are redacted
+const SENSITIVE_NAMES: [&str; 6] = [
+    "authorization",
+    "proxy-authorization",
+    "cookie",
+    "set-cookie",
+    "x-api-key",
+    "x-auth-token",
+];
+
+/// REDACTED is the replacement of sensitive values
+const REDACTED: &str = "[redacted]";
+
+/// is_sensitive checks whether header or query parameter name may carry credentials
+fn is_sensitive(name: &str) -> bool {
+    let name = name.to_lowercase();
+    SENSITIVE_NAMES.contains(&name.as_str())
+        || ["token", "secret", "password", "signature"]
+            .iter()
+            .any(|s| name.contains(s))
+        || name.ends_with("key")
+}
+
+/// redact_headers converts headers to pairs, values of sensitive headers are redacted.
+/// Internal x-land-* headers, such as routing signature and limits, are dropped.
+pub fn redact_headers(headers: &HeaderMap) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .filter(|(k, _)| !k.as_str().starts_with("x-land-"))
+        .map(|(k, v)| {
+            let key = k.as_str().to_lowercase();
+            let value = if is_sensitive(&key) {
+                REDACTED.to_string()
+            } else {
+                String::from_utf8_lossy(v.as_bytes()).to_string()
+            };
+            (key, value)
+        })
+        .collect()
+}
+
+/// redact_uri redacts values of sensitive query parameters in uri, others are kept as is
+pub fn redact_uri(uri: &str) -> String {
+    let Some((path, query)) = uri.split_once('?') else {
+        return uri.to_string();
+    };
+    let query: Vec<String> = query
+        .split('&')
+        .map(|pair| {
+            // decode name to match encoded parameter names, such as api%5Fkey
+            let name = form_urlencoded::parse(pair.as_bytes())
+                .next()
+                .map(|(k, _)| k.to_string())
+                .unwrap_or_default();
+            if !is_sensitive(&name) {
+                return pair.to_string();
+            }
+            let raw_name = pair.split_once('=').map_or(pair, |(k, _)| k);
+            format!("{}={}", raw_name, REDACTED)
+        })
+        .collect();
+    format!("{}?{}", path, query.join("&"))
+}
+
+/// body_preview converts body bytes to text truncated to MAX_BODY_SIZE
+pub fn body_preview(bytes: &[u8]) -> String {
+    if bytes.len() <= MAX_BODY_SIZE {
+        return String::from_utf8_lossy(bytes).to_string();
+    }
+    format!(
+        "{}...(truncated, {} bytes)",
+        String::from_utf8_lossy(&bytes[..MAX_BODY_SIZE]),
+        bytes.len()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_uri() {
+        assert_eq!(redact_uri("http://a.com/p"), "http://a.com/p");
+        assert_eq!(
+            redact_uri("http://a.com/p?id=1&token=abc&api_key=k&API%5FKEY=k2&q=x"),
+            "http://a.com/p?id=1&token=[redacted]&api_key=[redacted]&API%5FKEY=[redacted]&q=x"
+        );
+        assert_eq!(
+            redact_uri("http://a.com/?access_token=abc&password"),
+            "http://a.com/?access_token=[redacted]&password=[redacted]"
+        );
+    }
+
+    #[test]
+    fn test_redact_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert("authorization", "Bearer abc".parse().unwrap());
+        headers.insert("x-app-secret", "abc".parse().unwrap());
+        headers.insert("content-type", "text/plain".parse().unwrap());
+        headers.insert("x-land-sig", "abc".parse().unwrap());
+        headers.insert("x-land-pid", "1".parse().unwrap());
+        let pairs = redact_headers(&headers);
+        assert_eq!(pairs.len(), 3);
+        for (k, v) in pairs {
+            if k == "content-type" {
+                assert_eq!(v, "text/plain");
+            } else {
+                assert_eq!(v, REDACTED);
+            }
+        }
+    }
+}
@@ -1,19 +1,26 @@
 use anyhow::Result;
 use axum::{http::StatusCode, response::IntoResponse, routing::any, Router};
 use land_wasm_host::{
-    hostcall::init_clients,
+    egress::{self, Policy},
     init_engines,
-    pool::{prepare_worker, FILE_DIR},
+    pool::{init_cache, prepare_worker, DEFAULT_MAX_WORKERS, FILE_DIR},
+    EngineOpts,
 };
-use metrics_exporter_prometheus::PrometheusBuilder;
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder};
 use once_cell::sync::OnceCell;
 use std::{net::SocketAddr, time::Duration};
 use tower_http::timeout::TimeoutLayer;
 use tracing::{debug, info};
 
 mod handle;
+mod inspect;
 mod middle;
 
+/// LATENCY_BUCKETS are histogram buckets of latency metrics in milliseconds
+const LATENCY_BUCKETS: &[f64] = &[
+    0.5, 1.0, 2.5, 5.0, 10.0, 25.0, 50.0, 100.0, 250.0, 500.0, 1000.0, 2500.0, 5000.0, 10000.0,
+];
+
 /// Opts for the worker server
 pub struct Opts {
     pub addr: SocketAddr,
@@ -23,6 +30,16 @@ pub struct Opts {
     pub enable_wasmtime_aot: bool,
     pub enable_metrics: bool,
     pub metrics_addr: Option<String>,
+    pub egress_policy: Policy,
+    pub routing_secret: Option<String>,
+    /// secret to sign aot artifacts, sha256 digest is used if not set
+    pub aot_secret: Option<String>,
+    /// max count of cached workers, least recently used workers are evicted
+    pub max_workers: u64,
+    /// settings of wasmtime engine and pooling allocator
+    pub engine: EngineOpts,
+    /// route requests by host header with synced routes table, instead of trusting proxy headers
+    pub self_routing: bool,
 }
 
 impl Default for Opts {
@@ -35,6 +52,12 @@ impl Default for Opts {
             enable_wasmtime_aot: false,
             enable_metrics: false,
             metrics_addr: None,
+            egress_policy: Policy::default(),
+            routing_secret: None,
+            aot_secret: None,
+            max_workers: DEFAULT_MAX_WORKERS,
+            engine: EngineOpts::default(),
+            self_routing: false,
         }
     }
 }
@@ -43,6 +66,8 @@ static DEFAULT_WASM: OnceCell<String> = OnceCell::new();
 static ENDPOINT_NAME: OnceCell<String> = OnceCell::new();
 static ENABLE_WASMTIME_AOT: OnceCell<bool> = OnceCell::new();
 static ENABLE_METRICS: OnceCell<bool> = OnceCell::new();
+static ROUTING_SECRET: OnceCell<String> = OnceCell::new();
+static SELF_ROUTING: OnceCell<bool> = OnceCell::new();
 
 async fn init_opts(opts: &Opts) -> Result<()> {
     let hostname = if let Some(endpoint) = &opts.endpoint_name {
@@ -56,6 +81,11 @@ async fn init_opts(opts: &Opts) -> Result<()> {
     debug!("Default wasm: {:?}", opts.default_wasm);
     debug!("Enable Wasmtime AOT: {}", opts.enable_wasmtime_aot);
     debug!("Enable Metrics: {}", opts.enable_metrics);
+    debug!("Egress policy: {:?}", opts.egress_policy);
+    debug!("Verify routing signature: {}", opts.routing_secret.is_some());
+    debug!("Sign AOT artifacts: {}", opts.aot_secret.is_some());
+    debug!("Max workers: {}", opts.max_workers);
+    debug!("Self routing: {}", opts.self_routing);
 
     // create directory
     std::fs::create_dir_all(&opts.dir).unwrap();
@@ -65,9 +95,16 @@ async fn init_opts(opts: &Opts) -> Result<()> {
     ENABLE_WASMTIME_AOT.set(opts.enable_wasmtime_aot).unwrap();
     ENABLE_METRICS.set(opts.enable_metrics).unwrap();
     FILE_DIR.set(opts.dir.clone()).unwrap();
+    init_cache(opts.max_workers);
+    SELF_ROUTING.set(opts.self_routing).unwrap();
+    if let Some(secret) = &opts.routing_secret {
+        ROUTING_SECRET.set(secret.clone()).unwrap();
+    }
+    if let Some(secret) = &opts.aot_secret {
+        land_wasm_host::aot::init_secret(secret);
+    }
 
-    init_clients();
-    init_engines()?;
+    egress::init_policy(opts.egress_policy.clone());
 
     if opts.enable_metrics {
         let addr: SocketAddr = opts
@@ -76,12 +113,41 @@ async fn init_opts(opts: &Opts) -> Result<()> {
             .unwrap_or_else(|| "127.0.0.1:9000".to_string())
             .parse()
             .unwrap();
-        PrometheusBuilder::new()
-            .with_http_listener(addr)
-            .install()?;
+        let mut builder = PrometheusBuilder::new().with_http_listener(addr);
+        // cpu time in milliseconds and memory in bytes, limits are usually in these ranges
+        builder = builder
+            .set_buckets_for_metric(
+                Matcher::Full("req_fn_cpu_ms".to_string()),
+                &[1.0, 5.0, 10.0, 25.0, 50.0, 100.0, 250.0, 500.0, 1000.0],
+            )?
+            .set_buckets_for_metric(
+                Matcher::Full("req_fn_memory_bytes".to_string()),
+                &[
+                    1048576.0,
+                    4194304.0,
+                    16777216.0,
+                    33554432.0,
+                    67108864.0,
+                    134217728.0,
+                ],
+            )?;
+        // latency in milliseconds, cold starts compile wasm and take seconds
+        for name in [
+            "req_fn_duration_ms",
+            "req_fn_instantiate_ms",
+            "req_fn_execute_ms",
+            "worker_load_ms",
+        ] {
+            builder =
+                builder.set_buckets_for_metric(Matcher::Full(name.to_string()), LATENCY_BUCKETS)?;
+        }
+        builder.install()?;
         info!("Metrics server started at {}", addr);
     }
 
+    // init engines after metrics, it reports engine settings
+    init_engines(opts.engine.clone())?;
+
     Ok(())
 }
 
@@ -92,17 +158,28 @@ async fn load_default_wasm() -> Result<()> {
         return Ok(());
     }
     let aot_enable = ENABLE_WASMTIME_AOT.get().unwrap();
-    let _ = prepare_worker(default_wasm, *aot_enable).await?;
+    // default wasm is served as deployment 0
+    let _ = prepare_worker("0", default_wasm, *aot_enable).await?;
     Ok(())
 }
 
-/// start worker server
-pub async fn start(opts: Opts) -> Result<()> {
-    init_opts(&opts).await?;
+/// init worker server opts and runtime, it should be called once before serve
+pub async fn init(opts: &Opts) -> Result<()> {
+    init_opts(opts).await?;
 
     // load default wasm
     load_default_wasm().await?;
+    Ok(())
+}
+
+/// start worker server
+pub async fn start(opts: Opts) -> Result<()> {
+    init(&opts).await?;
+    serve(opts).await
+}
 
+/// serve starts worker server after init
+pub async fn serve(opts: Opts) -> Result<()> {
     let app = Router::new()
         .route("/", any(handle::run))
         .route("/*path", any(handle::run))
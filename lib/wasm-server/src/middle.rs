@@ -1,7 +1,19 @@
-use crate::{DEFAULT_WASM, ENABLE_METRICS, ENDPOINT_NAME};
-use axum::{extract::Request, http::StatusCode, middleware::Next, response::Response};
-use metrics::{counter, Counter};
+use crate::{DEFAULT_WASM, ENABLE_METRICS, ENDPOINT_NAME, ROUTING_SECRET, SELF_ROUTING};
+use axum::{
+    extract::Request,
+    http::{HeaderMap, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use land_common::{
+    crypto,
+    routes::{self, Route, RouteLimits},
+};
+use land_wasm_host::{Limits, Stats};
+use metrics::{counter, histogram, Counter, Histogram};
 use serde::Serialize;
+use std::collections::BTreeMap;
+use tracing::warn;
 
 #[derive(Default, Clone, Serialize, Debug)]
 pub struct WorkerInfo {
@@ -12,6 +24,8 @@ pub struct WorkerInfo {
     pub deploy_id: String,
     pub host: String,
     pub endpoint: String,
+    #[serde(skip)]
+    pub limits: Limits,
 }
 
 #[derive(Clone)]
@@ -22,10 +36,19 @@ pub struct WorkerMetrics {
     pub req_fn_error_total: Counter,
     pub req_fn_in_bytes_total: Counter,
     pub req_fn_out_bytes_total: Counter,
+    pub req_fn_cpu_ms: Histogram,
+    pub req_fn_cpu_ms_total: Counter,
+    pub req_fn_memory_bytes: Histogram,
+    pub req_fn_fetch_total: Counter,
+    pub req_fn_duration_ms: Histogram,
+    pub req_fn_instantiate_ms: Histogram,
+    pub req_fn_execute_ms: Histogram,
+    pub req_fn_cold_start_total: Counter,
+    pub req_fn_warm_start_total: Counter,
 }
 
 impl WorkerMetrics {
-    pub fn new(pid: String, uid: String, did: String, ep: String) -> Self {
+    pub fn new(pid: String, uid: String, did: String, ep: String, canary: bool) -> Self {
         if !ENABLE_METRICS.get().unwrap() {
             let noop = Counter::noop();
             return WorkerMetrics {
@@ -34,10 +57,26 @@ impl WorkerMetrics {
                 req_fn_success_total: noop.clone(),
                 req_fn_error_total: noop.clone(),
                 req_fn_in_bytes_total: noop.clone(),
-                req_fn_out_bytes_total: noop,
+                req_fn_out_bytes_total: noop.clone(),
+                req_fn_cpu_ms: Histogram::noop(),
+                req_fn_cpu_ms_total: noop.clone(),
+                req_fn_memory_bytes: Histogram::noop(),
+                req_fn_fetch_total: noop.clone(),
+                req_fn_duration_ms: Histogram::noop(),
+                req_fn_instantiate_ms: Histogram::noop(),
+                req_fn_execute_ms: Histogram::noop(),
+                req_fn_cold_start_total: noop.clone(),
+                req_fn_warm_start_total: noop,
             };
         }
-        let labels = vec![("pid", pid), ("uid", uid), ("did", did), ("ep", ep)];
+        // did and canary labels are used to compare stable and canary deployments
+        let labels = vec![
+            ("pid", pid),
+            ("uid", uid),
+            ("did", did),
+            ("ep", ep),
+            ("canary", canary.to_string()),
+        ];
         let mut req_fn_total_labels = labels.clone();
         req_fn_total_labels.push(("typ", "all".to_string()));
         let mut req_fn_notfound_total_labels = labels.clone();
@@ -50,6 +89,10 @@ impl WorkerMetrics {
         req_fn_in_bytes_total_labels.push(("typ", "main_in_bytes".to_string()));
         let mut req_fn_out_bytes_total_labels = labels.clone();
         req_fn_out_bytes_total_labels.push(("typ", "main_out_bytes".to_string()));
+        let mut req_fn_cold_start_total_labels = labels.clone();
+        req_fn_cold_start_total_labels.push(("typ", "cold".to_string()));
+        let mut req_fn_warm_start_total_labels = labels.clone();
+        req_fn_warm_start_total_labels.push(("typ", "warm".to_string()));
         WorkerMetrics {
             req_fn_total: counter!("req_fn_total", &req_fn_total_labels),
             req_fn_notfound_total: counter!("req_fn_total", &req_fn_notfound_total_labels),
@@ -57,60 +100,167 @@ impl WorkerMetrics {
             req_fn_error_total: counter!("req_fn_total", &req_fn_error_total_labels),
             req_fn_in_bytes_total: counter!("req_fn_bytes", &req_fn_in_bytes_total_labels),
             req_fn_out_bytes_total: counter!("req_fn_bytes", &req_fn_out_bytes_total_labels),
+            req_fn_cpu_ms: histogram!("req_fn_cpu_ms", &labels),
+            req_fn_cpu_ms_total: counter!("req_fn_cpu_ms_total", &labels),
+            req_fn_memory_bytes: histogram!("req_fn_memory_bytes", &labels),
+            req_fn_fetch_total: counter!("req_fn_fetch_total", &labels),
+            req_fn_duration_ms: histogram!("req_fn_duration_ms", &labels),
+            req_fn_instantiate_ms: histogram!("req_fn_instantiate_ms", &labels),
+            req_fn_execute_ms: histogram!("req_fn_execute_ms", &labels),
+            req_fn_cold_start_total: counter!(
+                "req_fn_start_total",
+                &req_fn_cold_start_total_labels
+            ),
+            req_fn_warm_start_total: counter!(
+                "req_fn_start_total",
+                &req_fn_warm_start_total_labels
+            ),
         }
     }
+
+    /// record_stats records the resources that a request used
+    pub fn record_stats(&self, stats: &Stats) {
+        let cpu_ms = stats.cpu_time.as_millis() as u64;
+        self.req_fn_cpu_ms.record(cpu_ms as f64);
+        self.req_fn_cpu_ms_total.increment(cpu_ms);
+        self.req_fn_memory_bytes.record(stats.memory as f64);
+        self.req_fn_fetch_total.increment(stats.fetch_count as u64);
+        // no latency to record if instantiating fails
+        if let Some(instantiate) = stats.instantiate {
+            self.req_fn_instantiate_ms
+                .record(instantiate.as_secs_f64() * 1000.0);
+        }
+        if let Some(execute) = stats.execute {
+            self.req_fn_execute_ms
+                .record(execute.as_secs_f64() * 1000.0);
+        }
+    }
+
+    /// record_start records whether the request starts with a cold worker
+    pub fn record_start(&self, cold: bool) {
+        if cold {
+            self.req_fn_cold_start_total.increment(1);
+        } else {
+            self.req_fn_warm_start_total.increment(1);
+        }
+    }
+}
+
+/// read_limit reads positive number limit value from header
+fn read_limit(headers: &HeaderMap, key: &str) -> Option<u64> {
+    headers
+        .get(key)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok())
+        .filter(|v| *v > 0)
+}
+
+/// read_limits reads runtime limits from headers.
+/// cpu time is in milliseconds, memory is in MB, wall time is in seconds
+fn read_limits(headers: &HeaderMap) -> RouteLimits {
+    RouteLimits {
+        cpu_time: read_limit(headers, "x-land-cpu"),
+        memory: read_limit(headers, "x-land-mem"),
+        wall_time: read_limit(headers, "x-land-wall"),
+        fetch_count: read_limit(headers, "x-land-fetch"),
+    }
+}
+
+/// verify_signature verifies the signature of x-land-* headers set by traefik
+fn verify_signature(secret: &str, headers: &HeaderMap) -> bool {
+    let mut values = BTreeMap::new();
+    for (key, value) in headers.iter() {
+        if !key.as_str().starts_with("x-land-") {
+            continue;
+        }
+        let Ok(value) = value.to_str() else {
+            return false;
+        };
+        values.insert(key.to_string(), value.to_string());
+    }
+    let Some(signature) = values.get(crypto::SIGNATURE_HEADER) else {
+        return false;
+    };
+    crypto::verify_headers(secret, &values, signature)
+}
+
+/// read_header reads string value from header
+fn read_header(headers: &HeaderMap, key: &str, default: &str) -> String {
+    headers
+        .get(key)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or(default)
+        .to_string()
+}
+
+/// route_from_headers reads route from x-land-* headers set by proxy
+fn route_from_headers(headers: &HeaderMap) -> Route {
+    let default_wasm_path = DEFAULT_WASM.get().unwrap();
+    Route {
+        wasm_module: read_header(headers, "x-land-m", default_wasm_path),
+        user_id: read_header(headers, "x-land-uid", "0"),
+        project_id: read_header(headers, "x-land-pid", "0"),
+        deploy_id: read_header(headers, "x-land-did", "0"),
+        preview: read_header(headers, "x-land-preview", "") == "1",
+        limits: read_limits(headers),
+    }
+}
+
+/// route_from_host finds route by host in routes table, fallback to default wasm
+fn route_from_host(host: &str) -> Route {
+    routes::get(host).unwrap_or_else(|| Route {
+        wasm_module: DEFAULT_WASM.get().unwrap().clone(),
+        user_id: "0".to_string(),
+        project_id: "0".to_string(),
+        deploy_id: "0".to_string(),
+        preview: false,
+        limits: RouteLimits::default(),
+    })
 }
 
 /// worker_info to get worker info
 pub async fn worker_info(mut request: Request, next: Next) -> Result<Response, StatusCode> {
     let req_id = xid::new().to_string();
     let headers = request.headers().clone();
+    let host = read_header(&headers, "host", "unknown");
 
-    // get module path
-    let default_wasm_path = DEFAULT_WASM.get().unwrap();
-    let wasm_module = headers
-        .get("x-land-m")
-        .and_then(|v| v.to_str().ok())
-        .unwrap_or(default_wasm_path.as_str())
-        .to_string();
-
-    // get user-id, project-id, deploy-id, host
-    let user_id = headers
-        .get("x-land-uid")
-        .and_then(|v| v.to_str().ok())
-        .unwrap_or("0")
-        .to_string();
-    let project_id = headers
-        .get("x-land-pid")
-        .and_then(|v| v.to_str().ok())
-        .unwrap_or("0")
-        .to_string();
-    let deploy_id = headers
-        .get("x-land-did")
-        .and_then(|v| v.to_str().ok())
-        .unwrap_or("0")
-        .to_string();
-    let host = headers
-        .get("host")
-        .and_then(|v| v.to_str().ok())
-        .unwrap_or("unknown")
-        .to_string();
+    let route = if *SELF_ROUTING.get().unwrap() {
+        route_from_host(&host)
+    } else {
+        // reject requests not routed by traefik
+        if let Some(secret) = ROUTING_SECRET.get() {
+            if !verify_signature(secret, &headers) {
+                warn!(req_id, uri = %request.uri(), "Invalid routing signature");
+                return Err(StatusCode::UNAUTHORIZED);
+            }
+        }
+        route_from_headers(&headers)
+    };
+    // split traffic to canary deployment of the project by weight
+    let (route, canary) = routes::split(route);
 
     let endpoint = ENDPOINT_NAME.get().unwrap().to_string();
     let metrics = WorkerMetrics::new(
-        project_id.clone(),
-        user_id.clone(),
-        deploy_id.clone(),
+        route.project_id.clone(),
+        route.user_id.clone(),
+        route.deploy_id.clone(),
         endpoint.clone(),
+        canary,
     );
     let info = WorkerInfo {
         req_id,
-        wasm_module,
-        user_id,
-        project_id,
-        deploy_id,
+        wasm_module: route.wasm_module,
+        user_id: route.user_id,
+        project_id: route.project_id,
+        deploy_id: route.deploy_id,
         host,
         endpoint,
+        limits: Limits::new(
+            route.limits.cpu_time,
+            route.limits.memory,
+            route.limits.wall_time,
+            route.limits.fetch_count,
+        ),
     };
 
     request.extensions_mut().insert(info);